@@ -30,14 +30,14 @@ where
     pub fn from_buf(buf: Vec<M>) -> Self {
         Self { 
             buf,
-            _t: PhantomData::default()
+            _t: PhantomData
         }
     }
 
     pub fn new() -> Self {
         Self { 
             buf: Vec::new(), 
-            _t: PhantomData::default() 
+            _t: PhantomData 
         }
     }
 
@@ -45,6 +45,15 @@ where
         self.buf.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// Returns the shape of every element
+    pub fn shapes(&self) -> impl ExactSizeIterator<Item = (usize, usize)> + '_ {
+        self.buf.iter().map(|m| m.shape())
+    }
+
     fn to_index(&self, index: IndexType) -> usize {
         match index {
             IndexType::Back(i) => self.len() - (i + 1),
@@ -82,6 +91,16 @@ where
     }
 }
 
+impl<M, N> Default for Array<M, N>
+where
+    N: Num,
+    M: LinAlg<N>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<M, N> Index<usize> for Array<M, N>
 where
     N: Num,
@@ -0,0 +1,285 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use serde_derive::{Serialize, Deserialize};
+
+use crate::net::Net;
+use crate::history::{CSV_HEADER, csv_row};
+use crate::regression::RegressionScores;
+
+/// Training measurements of a single epoch
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Metrics {
+    pub epoch: usize,
+
+    // mean training cost
+    pub loss: f32,
+
+    // training accuracy evaluated after the epoch's last update,
+    // subset accuracy for multi-label and zero for regression
+    pub accuracy: f32,
+
+    // mean validation cost
+    pub valid_loss: Option<f32>,
+
+    // validation accuracy
+    pub valid_accuracy: Option<f32>,
+
+    // training regression scores evaluated after the epoch's last update
+    #[serde(default)]
+    pub regression: Option<RegressionScores>,
+
+    // validation regression scores
+    #[serde(default)]
+    pub valid_regression: Option<RegressionScores>,
+
+    // mean norm of the batch gradients before clipping
+    #[serde(default)]
+    pub grad_norm: f32,
+
+    // learning coefficient used during the epoch
+    pub learn_rate: f32,
+
+    // wall time of the epoch in seconds
+    pub time: f32,
+}
+
+impl Metrics {
+    /// Returns the validation loss, or the training loss without validation
+    pub fn monitored_loss(&self) -> f32 {
+        self.valid_loss.unwrap_or(self.loss)
+    }
+}
+
+/// Returned by callbacks to continue or terminate training
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Signal {
+    Continue,
+    Stop
+}
+
+/// Hooks invoked by `Net::train_with` throughout training
+pub trait Callback<const L: usize> {
+    fn on_train_begin(&mut self, _net: &mut Net<L>) -> Signal {
+        Signal::Continue
+    }
+
+    fn on_train_end(&mut self, _net: &mut Net<L>) {}
+
+    fn on_epoch_begin(&mut self, _epoch: usize, _epochs: usize, _net: &mut Net<L>) -> Signal {
+        Signal::Continue
+    }
+
+    fn on_epoch_end(&mut self, _metrics: &Metrics, _net: &mut Net<L>) -> Signal {
+        Signal::Continue
+    }
+
+    fn on_batch_end(&mut self, _epoch: usize, _batch: usize, _loss: f32, _net: &mut Net<L>) -> Signal {
+        Signal::Continue
+    }
+}
+
+/// Invokes every callback, returning whether any requested a stop
+pub(crate) fn dispatch<const L: usize, F>(callbacks: &mut [&mut dyn Callback<L>], mut hook: F) -> bool
+where
+    F: FnMut(&mut dyn Callback<L>) -> Signal
+{
+    let mut stop = false;
+
+    // every callback is invoked, even after a stop is requested
+    for callback in callbacks.iter_mut() {
+        stop |= hook(&mut **callback) == Signal::Stop;
+    }
+
+    stop
+}
+
+/// Prints epoch progress and metrics to the console
+pub struct Logger {
+    // controls printing of epochs
+    epochs: bool,
+
+    // controls printing of metrics
+    metrics: bool,
+
+    // number of finished epochs
+    finished: usize
+}
+
+impl Logger {
+    pub fn new(epochs: bool, metrics: bool) -> Self {
+        Self {
+            epochs,
+            metrics,
+            finished: 0
+        }
+    }
+}
+
+impl<const L: usize> Callback<L> for Logger {
+    fn on_epoch_begin(&mut self, epoch: usize, epochs: usize, _net: &mut Net<L>) -> Signal {
+        if self.epochs {
+            println!("epoch {} of {}", epoch+1, epochs);
+        }
+
+        Signal::Continue
+    }
+
+    fn on_epoch_end(&mut self, metrics: &Metrics, _net: &mut Net<L>) -> Signal {
+        self.finished = metrics.epoch + 1;
+
+        if !self.metrics {
+            return Signal::Continue
+        }
+
+        match metrics.regression {
+            Some(s) => println!(
+                "loss of {}, mse of {}, mae of {}, rmse of {}, r2 of {}, gradient norm of {}", 
+                metrics.loss, s.mse, s.mae, s.rmse, s.r2, metrics.grad_norm
+            ),
+            None => println!("loss of {}, accuracy of {}, gradient norm of {}", metrics.loss, metrics.accuracy, metrics.grad_norm)
+        }
+
+        if let (Some(loss), Some(s)) = (metrics.valid_loss, metrics.valid_regression) {
+            println!("validation loss of {}, mse of {}, mae of {}, rmse of {}, r2 of {}", loss, s.mse, s.mae, s.rmse, s.r2);
+        }
+
+        if let (Some(loss), Some(accuracy)) = (metrics.valid_loss, metrics.valid_accuracy) {
+            println!("validation loss of {}, accuracy of {}", loss, accuracy);
+        }
+
+        Signal::Continue
+    }
+
+    fn on_train_end(&mut self, _net: &mut Net<L>) {
+        if self.epochs {
+            println!("finished training after {} epochs", self.finished);
+        }
+    }
+}
+
+/// Writes the metrics of every epoch as a CSV row
+pub struct CsvLogger {
+    path: PathBuf,
+
+    file: Option<File>
+}
+
+impl CsvLogger {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: PathBuf::from(path),
+            file: None
+        }
+    }
+}
+
+impl CsvLogger {
+    /// Opens the log, appending to it for a resumed run
+    fn open(&self, resumed: bool) -> std::io::Result<File> {
+        if resumed && self.path.exists() {
+            return OpenOptions::new().append(true).open(&self.path)
+        }
+
+        let mut file = File::create(&self.path)?;
+        writeln!(file, "{}", CSV_HEADER)?;
+
+        Ok(file)
+    }
+}
+
+/// I/O failures are reported without interrupting training
+impl<const L: usize> Callback<L> for CsvLogger {
+    fn on_train_begin(&mut self, net: &mut Net<L>) -> Signal {
+        match self.open(net.progress().in_flight()) {
+            Ok(file) => self.file = Some(file),
+            Err(err) => eprintln!("couldn't open csv log {}: {}", self.path.display(), err)
+        }
+
+        Signal::Continue
+    }
+
+    fn on_epoch_end(&mut self, metrics: &Metrics, _net: &mut Net<L>) -> Signal {
+        let Some(file) = self.file.as_mut() else {
+            return Signal::Continue
+        };
+
+        // the log stops at the first failed row instead of leaving gaps
+        if let Err(err) = writeln!(file, "{}", csv_row(metrics)) {
+            eprintln!("couldn't write to csv log {}: {}", self.path.display(), err);
+            self.file = None;
+        }
+
+        Signal::Continue
+    }
+
+    fn on_train_end(&mut self, _net: &mut Net<L>) {
+        self.file = None;
+    }
+}
+
+/// Saves the model with its training progress to path after every
+/// epoch, or only when the monitored loss improves. Saved checkpoints
+/// can be resumed through `Net::resume_from`
+pub struct Checkpoint {
+    path: PathBuf,
+
+    best_only: bool,
+
+    best: Option<f32>,
+
+    // additionally saves every n batches
+    batches: Option<usize>
+}
+
+impl Checkpoint {
+    pub fn new(path: &str, best_only: bool) -> Self {
+        Self {
+            path: PathBuf::from(path),
+            best_only,
+            best: None,
+            batches: None
+        }
+    }
+
+    pub fn with_batch_interval(&mut self, batches: usize) -> &mut Self {
+        self.batches = Some(batches.max(1));
+        self
+    }
+}
+
+impl Checkpoint {
+    /// Saves the checkpoint, reporting a failure without interrupting training
+    fn save<const L: usize>(&self, net: &Net<L>) {
+        if let Err(err) = net.save_checkpoint_to(&self.path) {
+            eprintln!("couldn't save checkpoint {}: {}", self.path.display(), err);
+        }
+    }
+}
+
+impl<const L: usize> Callback<L> for Checkpoint {
+    fn on_batch_end(&mut self, _epoch: usize, batch: usize, _loss: f32, net: &mut Net<L>) -> Signal {
+        if self.batches.is_some_and(|n| (batch + 1).is_multiple_of(n)) {
+            self.save(net);
+        }
+
+        Signal::Continue
+    }
+
+    fn on_epoch_end(&mut self, metrics: &Metrics, net: &mut Net<L>) -> Signal {
+        let loss = metrics.monitored_loss();
+
+        let improved = self.best.is_none_or(|best| loss < best);
+
+        if improved {
+            self.best = Some(loss);
+        }
+
+        if improved || !self.best_only {
+            self.save(net);
+        }
+
+        Signal::Continue
+    }
+}
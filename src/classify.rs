@@ -0,0 +1,193 @@
+use serde_derive::{Serialize, Deserialize};
+
+use crate::error::{NetError, Result};
+use crate::evaluation::Evaluation;
+use crate::linalg::*;
+use crate::model::Model;
+use crate::multilabel::{self, MultiLabel};
+use crate::net::Net;
+use crate::step::{softmax, Activation, Output};
+
+/// Class index with its probability
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Prediction {
+    pub class: usize,
+    pub prob: f32
+}
+
+/// Class prediction interface shared by trained networks
+pub trait Classifier {
+    /// Returns the raw network output
+    fn try_output(&self, input: &Vector) -> Result<Vector>;
+
+    /// Normalizes a raw network output into class probabilities
+    fn normalize_output(&self, out: &Vector) -> Vector;
+
+    fn output(&self, input: &Vector) -> Vector {
+        self.try_output(input)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn try_probabilities(&self, input: &Vector) -> Result<Vector> {
+        self.try_output(input)
+            .map(|out| self.normalize_output(&out))
+    }
+
+    /// Returns the output normalized into class probabilities
+    fn probabilities(&self, input: &Vector) -> Vector {
+        self.try_probabilities(input)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Returns the most probable class
+    fn predict_class(&self, input: &Vector) -> Prediction {
+        let probs = self.probabilities(input);
+        let class = probs.hot();
+
+        Prediction { class, prob: probs[class] }
+    }
+
+    /// Returns the k most probable classes in descending order
+    fn top_k(&self, input: &Vector, k: usize) -> Vec<Prediction> {
+        top_k(&self.probabilities(input), k)
+    }
+
+    /// Returns the most probable class, or abstains with `None`
+    /// if its probability is below threshold
+    fn classify(&self, input: &Vector, threshold: f32) -> Option<Prediction> {
+        Some(self.predict_class(input))
+            .filter(|prediction| prediction.prob >= threshold)
+    }
+
+    /// Returns the share of inputs whose most probable class is the target's
+    fn accuracy(&self, inputs: &[Vector], targets: &[Vector]) -> f32 {
+        self.try_accuracy(inputs, targets)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn try_accuracy(&self, inputs: &[Vector], targets: &[Vector]) -> Result<f32> {
+        if inputs.len() != targets.len() {
+            return Err(NetError::Length { inputs: inputs.len(), targets: targets.len() })
+        }
+
+        let mut correct = 0;
+
+        for (input, target) in inputs.iter().zip(targets) {
+            if self.try_output(input)?.hot() == target.hot() {
+                correct += 1;
+            }
+        }
+
+        Ok(correct as f32 / inputs.len() as f32)
+    }
+
+    /// Returns the classification report over a data set,
+    /// counting top-1 through top-k accuracy
+    fn evaluate(&self, inputs: &[Vector], targets: &[Vector], k: usize) -> Evaluation {
+        self.try_evaluate(inputs, targets, k)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn try_evaluate(&self, inputs: &[Vector], targets: &[Vector], k: usize) -> Result<Evaluation> {
+        if inputs.len() != targets.len() {
+            return Err(NetError::Length { inputs: inputs.len(), targets: targets.len() })
+        }
+
+        let probs = inputs
+            .iter()
+            .map(|input| self.try_probabilities(input))
+            .collect::<Result<Vec<_>>>()?;
+
+        Evaluation::try_from_probabilities(&probs, targets, k)
+    }
+
+    /// Returns every label whose independent output reaches its threshold,
+    /// a single threshold applies to all labels
+    fn predict_labels(&self, input: &Vector, thresholds: &[f32]) -> Vec<usize> {
+        multilabel::decide(&self.output(input), thresholds)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(label, decided)| decided.then_some(label))
+            .collect()
+    }
+
+    /// Returns the multi-label report over a data set of multi-hot targets
+    fn evaluate_labels(&self, inputs: &[Vector], targets: &[Vector], thresholds: &[f32]) -> MultiLabel {
+        self.try_evaluate_labels(inputs, targets, thresholds)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    fn try_evaluate_labels(&self, inputs: &[Vector], targets: &[Vector], thresholds: &[f32]) -> Result<MultiLabel> {
+        if inputs.len() != targets.len() {
+            return Err(NetError::Length { inputs: inputs.len(), targets: targets.len() })
+        }
+
+        let outs = inputs
+            .iter()
+            .map(|input| self.try_output(input))
+            .collect::<Result<Vec<_>>>()?;
+
+        MultiLabel::try_from_outputs(&outs, targets, thresholds)
+    }
+}
+
+impl<const L: usize> Classifier for Net<L> {
+    fn try_output(&self, input: &Vector) -> Result<Vector> {
+        self.try_predict(input)
+    }
+
+    fn normalize_output(&self, out: &Vector) -> Vector {
+        to_probabilities(out, self.data.act, self.data.out)
+    }
+}
+
+impl Classifier for Model {
+    fn try_output(&self, input: &Vector) -> Result<Vector> {
+        self.try_forward_prop(input)
+    }
+
+    fn normalize_output(&self, out: &Vector) -> Vector {
+        to_probabilities(out, self.act(), self.out())
+    }
+}
+
+/// Normalizes a network output into class probabilities:
+/// softmax outputs are kept, bounded outputs are shifted to [0, 1]
+/// and rescaled to sum to one, unbounded ones pass through softmax
+pub fn to_probabilities(out: &Vector, act: Activation, output: Output) -> Vector {
+    match (output, act) {
+        (Output::Softmax, _) => out.clone(),
+        (Output::Sigmoid, _) => normalize(out),
+        (Output::Act, Activation::Tanh) => normalize(&out.map(|n| (n + 1.) / 2.)),
+        (Output::Act, Activation::Sig) => normalize(out),
+        (Output::Act, Activation::Lin) => softmax(out)
+    }
+}
+
+/// Rescales non-negative scores to sum to one,
+/// uniform if there is no positive score
+fn normalize(scores: &Vector) -> Vector {
+    let scores = scores.map(|n| n.max(0.));
+    let sum: f32 = scores.buf().iter().sum();
+
+    if sum <= 0. {
+        return Vector::from_fill(scores.row(), 1. / scores.row() as f32)
+    }
+
+    scores.scale(1. / sum)
+}
+
+/// Returns the k highest probabilities in descending order
+pub fn top_k(probs: &Vector, k: usize) -> Vec<Prediction> {
+    let mut predictions: Vec<Prediction> = probs
+        .buf()
+        .iter()
+        .enumerate()
+        .map(|(class, prob)| Prediction { class, prob: *prob })
+        .collect();
+
+    predictions.sort_by(|a, b| b.prob.total_cmp(&a.prob));
+    predictions.truncate(k);
+
+    predictions
+}
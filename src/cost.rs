@@ -1,21 +1,37 @@
 use serde_derive::{Serialize, Deserialize};
 
+/// Bound keeping logarithms of the cross-entropy finite
+const LOG_EPSILON: f32 = 1e-6;
+
 /// Enumerated network cost function
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum Cost {
+    // squared difference
     Quad,
+
+    // binary cross-entropy of independent probabilities
+    BinCross
 }
 
 impl Cost {
-    pub fn value(&self, diff: f32) -> f32 {
+    pub fn value(&self, out: f32, target: f32) -> f32 {
         match self {
-            Cost::Quad => diff.powi(2)
+            Cost::Quad => (target - out).powi(2),
+            Cost::BinCross => {
+                let out = out.clamp(LOG_EPSILON, 1. - LOG_EPSILON);
+                -(target * out.ln() + (1. - target) * (1. - out).ln())
+            }
         }
     }
 
-    pub fn deriv(&self,  diff: f32) -> f32 {
+    /// Returns the negative cost derivative with respect to the output
+    pub fn deriv(&self, out: f32, target: f32) -> f32 {
         match self {
-            Cost::Quad => 2. * diff
+            Cost::Quad => 2. * (target - out),
+            Cost::BinCross => {
+                let out = out.clamp(LOG_EPSILON, 1. - LOG_EPSILON);
+                (target - out) / (out * (1. - out))
+            }
         }
     }
-}
\ No newline at end of file
+}
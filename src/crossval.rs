@@ -0,0 +1,327 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use serde_derive::Serialize;
+
+use crate::classify::Classifier;
+use crate::error::{NetError, Result};
+use crate::format;
+use crate::linalg::*;
+use crate::multilabel::THRESHOLD;
+use crate::net::{HyperData, Net, Task};
+use crate::progress::Shuffler;
+
+/// K-fold cross-validation training a fresh net of a template per fold
+pub struct CrossValidation<const L: usize> {
+    // hyper parameters of every fold's net
+    template: HyperData<L>,
+
+    folds: usize,
+
+    epochs: usize,
+
+    // controls keeping the class, or label set, proportions of the data in every fold
+    stratified: bool,
+
+    // seed of the fold assignment, random if unset
+    seed: Option<u64>
+}
+
+impl<const L: usize> CrossValidation<L> {
+    pub fn new(template: &HyperData<L>, folds: usize, epochs: usize) -> Self {
+        Self {
+            template: template.clone(),
+            folds,
+            epochs,
+            stratified: false,
+            seed: None
+        }
+    }
+
+    pub fn with_stratified(&mut self, state: bool) -> &mut Self {
+        self.stratified = state;
+        self
+    }
+
+    /// Makes the fold assignment reproducible
+    pub fn with_seed(&mut self, seed: u64) -> &mut Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Returns the sample indices of every fold, stratified by the target
+    /// classes or label sets if enabled, which regression targets lack
+    pub fn split(&self, targets: &[Vector]) -> Result<Vec<Vec<usize>>> {
+        if self.folds < 2 || self.folds > targets.len() {
+            return Err(NetError::FoldCount { folds: self.folds, samples: targets.len() })
+        }
+
+        if self.stratified && self.template.task == Task::Regression {
+            return Err(NetError::Config("stratified folds need classification or multi-label targets"))
+        }
+
+        let mut rng = self.seed.map_or_else(Shuffler::default, Shuffler::new);
+        let order = rng.permutation(targets.len());
+
+        // grouping the shuffled samples by class before dealing them
+        // out spreads every class evenly over the folds
+        let groups: Vec<Vec<usize>> = if self.stratified {
+            let mut classes = BTreeMap::new();
+
+            for i in order {
+                let class: Vec<usize> = match self.template.task {
+                    Task::MultiLabel => (0..targets[i].row()).filter(|l| targets[i][*l] >= THRESHOLD).collect(),
+                    _ => vec![targets[i].hot()]
+                };
+
+                classes.entry(class).or_insert_with(Vec::new).push(i);
+            }
+
+            classes.into_values().collect()
+        }
+        else {
+            vec![order]
+        };
+
+        let mut folds = vec![Vec::new(); self.folds];
+
+        for (n, i) in groups.into_iter().flatten().enumerate() {
+            folds[n % self.folds].push(i);
+        }
+
+        Ok(folds)
+    }
+
+    pub fn run(&self, inputs: &[Vector], targets: &[Vector]) -> CrossValReport {
+        self.try_run(inputs, targets)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Trains on all folds but one and evaluates on the held-out fold,
+    /// once for every fold, scoring the folds by the template's task
+    pub fn try_run(&self, inputs: &[Vector], targets: &[Vector]) -> Result<CrossValReport> {
+        // fold nets never save themselves, as they would share their file
+        let mut template = self.template.clone();
+        template.with_save_best(false).with_dir("");
+
+        template.check_training(inputs, targets, None)?;
+
+        let folds = self.split(targets)?;
+        let mut scores = Vec::with_capacity(folds.len());
+
+        for (k, held_out) in folds.iter().enumerate() {
+            let (mut train_inputs, mut train_targets) = (Vec::new(), Vec::new());
+
+            for i in folds.iter().enumerate().filter(|(j, _)| *j != k).flat_map(|(_, fold)| fold) {
+                train_inputs.push(inputs[*i].clone());
+                train_targets.push(targets[*i].clone());
+            }
+
+            let test_inputs: Vec<Vector> = held_out.iter().map(|i| inputs[*i].clone()).collect();
+            let test_targets: Vec<Vector> = held_out.iter().map(|i| targets[*i].clone()).collect();
+
+            let mut net = Net::try_from_parts(template.clone())?;
+            net.try_train(&train_inputs, &train_targets, self.epochs)?;
+
+            scores.push(FoldScores {
+                samples: held_out.len(),
+                loss: net.loss(&test_inputs, &test_targets),
+                scores: self.scores(&net, &test_inputs, &test_targets)?
+            });
+        }
+
+        Ok(CrossValReport::from_folds(self.template.task, scores))
+    }
+
+    /// Returns the scores of the net on a held-out fold, in the order
+    /// of the columns of the task
+    fn scores(&self, net: &Net<L>, inputs: &[Vector], targets: &[Vector]) -> Result<Vec<f32>> {
+        let scores = match self.template.task {
+            Task::Classification => {
+                let evaluation = net.try_evaluate(inputs, targets, 1)?;
+                let avg = evaluation.macro_avg;
+
+                vec![evaluation.accuracy(), avg.precision, avg.recall, avg.f1]
+            }
+            Task::Regression => {
+                let total = net.try_regression(inputs, targets)?.total;
+
+                vec![total.mse, total.mae, total.rmse, total.r2]
+            }
+            Task::MultiLabel => {
+                let labels = net.try_evaluate_labels(inputs, targets, &[])?;
+                let avg = labels.macro_avg;
+
+                vec![labels.subset_accuracy, labels.hamming_loss, avg.precision, avg.recall, avg.f1]
+            }
+        };
+
+        Ok(scores)
+    }
+}
+
+/// Returns the names of the scores of a task, averaging per-class
+/// and per-label scores as macro averages
+fn columns(task: Task) -> &'static [&'static str] {
+    match task {
+        Task::Classification => &["accuracy", "precision", "recall", "f1"],
+        Task::Regression => &["mse", "mae", "rmse", "r2"],
+        Task::MultiLabel => &["subset", "hamming", "precision", "recall", "f1"]
+    }
+}
+
+/// Scores of a net on its held-out fold
+#[derive(Clone, Default, Debug, Serialize)]
+pub struct FoldScores {
+    pub samples: usize,
+
+    // mean cost
+    pub loss: f32,
+
+    // scores of the task, named by the report's columns
+    pub scores: Vec<f32>
+}
+
+impl FoldScores {
+    fn metrics(&self) -> impl Iterator<Item = f32> + '_ {
+        std::iter::once(self.loss).chain(self.scores.iter().copied())
+    }
+
+    fn from_metrics(samples: usize, metrics: Vec<f32>) -> Self {
+        Self { samples, loss: metrics[0], scores: metrics[1..].to_vec() }
+    }
+}
+
+/// Per-fold scores of a cross-validation with their mean and
+/// sample standard deviation
+#[derive(Clone, Debug, Serialize)]
+pub struct CrossValReport {
+    // names of the task scores
+    pub columns: &'static [&'static str],
+
+    pub folds: Vec<FoldScores>,
+
+    pub mean: FoldScores,
+
+    pub std: FoldScores
+}
+
+impl CrossValReport {
+    fn from_folds(task: Task, folds: Vec<FoldScores>) -> Self {
+        let columns = columns(task);
+
+        let k = folds.len() as f32;
+        let samples = folds.iter().map(|fold| fold.samples).sum();
+
+        let mut mean = vec![0.; columns.len() + 1];
+        let mut var = vec![0.; columns.len() + 1];
+
+        for fold in folds.iter() {
+            for (m, n) in mean.iter_mut().zip(fold.metrics()) {
+                *m += n / k;
+            }
+        }
+
+        for fold in folds.iter() {
+            for ((v, m), n) in var.iter_mut().zip(&mean).zip(fold.metrics()) {
+                *v += (n - m).powi(2) / (k - 1.).max(1.);
+            }
+        }
+
+        Self {
+            columns,
+            mean: FoldScores::from_metrics(samples, mean),
+            std: FoldScores::from_metrics(samples, var.into_iter().map(f32::sqrt).collect()),
+            folds
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        format::to_json(self)
+    }
+
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_json(self, path)
+    }
+}
+
+impl fmt::Display for CrossValReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>9} {:>9}", "fold", "loss")?;
+
+        for column in self.columns {
+            write!(f, " {:>9}", column)?;
+        }
+
+        writeln!(f, " {:>9}", "samples")?;
+
+        let row = |f: &mut fmt::Formatter<'_>, name: &str, s: &FoldScores| {
+            write!(f, "{:>9} {:>9.4}", name, s.loss)?;
+
+            for score in s.scores.iter() {
+                write!(f, " {:>9.3}", score)?;
+            }
+
+            writeln!(f, " {:>9}", s.samples)
+        };
+
+        for (k, fold) in self.folds.iter().enumerate() {
+            row(f, &(k+1).to_string(), fold)?;
+        }
+
+        writeln!(f)?;
+        row(f, "mean", &self.mean)?;
+        row(f, "std", &self.std)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inputs() -> Vec<Vector> {
+        (0..12).map(|i| Vector::from_arr([(i as f32).sin(), (i as f32 * 0.5).cos()])).collect()
+    }
+
+    #[test]
+    fn folds_are_scored_by_the_task() {
+        let inputs = inputs();
+        let targets: Vec<Vector> = inputs.iter().map(|x| Vector::from_arr([x[0] * 0.5, x[1] * 0.5])).collect();
+
+        let mut data = HyperData::from([2, 3, 2]);
+        data.with_task(Task::Regression);
+
+        let report = CrossValidation::new(&data, 3, 2).with_seed(7).try_run(&inputs, &targets).unwrap();
+
+        assert_eq!(report.columns, &["mse", "mae", "rmse", "r2"]);
+        assert!(report.folds.iter().chain([&report.mean, &report.std]).all(|fold| fold.scores.len() == 4));
+        assert_eq!(report.mean.samples, 12);
+    }
+
+    #[test]
+    fn regression_folds_cannot_be_stratified() {
+        let mut data = HyperData::from([2, 3, 2]);
+        data.with_task(Task::Regression);
+
+        let mut crossval = CrossValidation::new(&data, 3, 2);
+        crossval.with_stratified(true);
+
+        assert!(matches!(crossval.split(&inputs()), Err(NetError::Config(_))));
+    }
+
+    #[test]
+    fn fold_nets_never_save_themselves() {
+        let inputs = inputs();
+        let targets: Vec<Vector> = (0..12).map(|i| Vector::one_hot(2, i % 2)).collect();
+
+        let dir = std::env::temp_dir().join(format!("net-rs-crossval-{}.json", std::process::id()));
+
+        let mut data = HyperData::from([2, 3, 2]);
+        data.with_save_best(true).with_dir(dir.to_str().unwrap());
+
+        let report = CrossValidation::new(&data, 3, 2).try_run(&inputs, &targets);
+
+        assert!(report.is_ok() && !dir.exists());
+    }
+}
@@ -0,0 +1,132 @@
+use serde_derive::{Serialize, Deserialize};
+
+use crate::array::IndexType::Back;
+use crate::callback::{Callback, Logger};
+use crate::error::NetError;
+use crate::history::History;
+use crate::linalg::*;
+use crate::net::Net;
+use crate::step::softmax;
+
+/// Default softening temperature
+const TEMPERATURE: f32 = 4.;
+/// Default weight of the true labels
+const ALPHA: f32 = 0.5;
+
+/// Temperature and mixing weight of knowledge distillation
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Distillation {
+    // softening temperature of the teacher and student outputs
+    pub temperature: f32,
+
+    // weight of the true labels, the soft targets get the remainder
+    pub alpha: f32
+}
+
+impl Default for Distillation {
+    fn default() -> Self {
+        Self::new(TEMPERATURE, ALPHA)
+    }
+}
+
+impl Distillation {
+    pub fn new(temperature: f32, alpha: f32) -> Self {
+        Self {
+            temperature: temperature.max(f32::EPSILON),
+            alpha: alpha.clamp(0., 1.)
+        }
+    }
+}
+
+/// Temperature-softened teacher outputs of a training set, computed
+/// once as the teacher doesn't change while the student trains
+pub struct SoftTargets {
+    targets: Vec<Vector>,
+
+    distillation: Distillation
+}
+
+impl SoftTargets {
+    pub fn new<const T: usize>(teacher: &Net<T>, inputs: &[Vector], distillation: Distillation) -> Self {
+        Self {
+            targets: teacher.soft_targets(inputs, distillation.temperature),
+            distillation
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+}
+
+impl<const L: usize> Net<L> {
+    /// Returns the output distributions softened by temperature
+    pub fn soft_targets(&self, inputs: &[Vector], temperature: f32) -> Vec<Vector> {
+        inputs
+            .iter()
+            .map(|input| softmax(&self.predict_logits(input).scale(1. / temperature)))
+            .collect()
+    }
+
+    /// Trains on a blend of the true targets and the teacher's
+    /// temperature-softened outputs
+    pub fn distill<const T: usize>(
+        &mut self,
+        teacher: &Net<T>,
+        inputs: &[Vector],
+        targets: &[Vector],
+        distillation: Distillation,
+        epochs: usize
+    ) -> History {
+        let soft = SoftTargets::new(teacher, inputs, distillation);
+
+        let mut logger = Logger::new(self.data.stat_epoch, self.data.stat_error);
+        self.train_distilled(inputs, targets, &soft, None, epochs, &mut [&mut logger])
+    }
+
+    /// Trains on a blend of the true and soft targets with an optional
+    /// validation set, invoking the given callbacks
+    pub fn train_distilled(
+        &mut self,
+        inputs: &[Vector],
+        targets: &[Vector],
+        soft: &SoftTargets,
+        valid: Option<(&[Vector], &[Vector])>,
+        epochs: usize,
+        callbacks: &mut [&mut dyn Callback<L>]
+    ) -> History {
+        if soft.len() != inputs.len() {
+            panic!("{}", NetError::Length { inputs: inputs.len(), targets: soft.len() })
+        }
+
+        let out_shape = (self.data.form[L-1], 1);
+
+        if let Some(target) = soft.targets.iter().find(|target| target.shape() != out_shape) {
+            panic!("invalid soft targets: {}", NetError::Shape { expected: out_shape, found: target.shape() })
+        }
+
+        self.train_loop(inputs, targets, Some(soft), valid, epochs, callbacks)
+    }
+
+    /// Back propagates the blended error of the target and the i-th soft target
+    pub(crate) fn back_prop_distilled(&mut self, input: &Vector, target: &Vector, soft: &SoftTargets, i: usize) {
+        self.clear_propagation_data();
+        self.forward_prop(input);
+        self.output_error(target);
+
+        let Distillation { temperature, alpha } = soft.distillation;
+
+        // soft error_L = T ( q_teacher - softmax ( sum_L / T ) ), the negative gradient
+        // of the T² scaled divergence keeping its magnitude independent of T
+        let student = softmax(&self.sums[Back(0)].scale(1. / temperature));
+        let mut soft_err = soft.targets[i].sub(&student);
+        soft_err.scale_eq(temperature * (1. - alpha));
+
+        self.err[Back(0)].scale_eq(alpha).add_eq(&soft_err);
+        self.back_prop_error();
+    }
+}
@@ -1,6 +1,6 @@
 use nannou::prelude::*;
 
-use crate::{net::Net, linalg::{Vector, LinAlgGen}};
+use crate::{net::Net, linalg::Vector, classify::Classifier};
 
 const COL: usize = 784;
 const ROW: usize = 784;
@@ -72,29 +72,24 @@ fn update(app: &App, model: &mut Model, _update: Update) {
         0
     };
 
-    match model.buf.get_mut(ROW_P * y as usize + x as usize) {
-        Some(p) => *p = state,
-        _ => ()
+    if let Some(p) = model.buf.get_mut(ROW_P * y as usize + x as usize) {
+        *p = state
     }
 
-    match model.buf.get_mut(ROW_P * y as usize + x as usize + 1) {
-        Some(p) => *p = state,
-        _ => ()
+    if let Some(p) = model.buf.get_mut(ROW_P * y as usize + x as usize + 1) {
+        *p = state
     }
 
-    match model.buf.get_mut(ROW_P * y as usize + x as usize - 1) {
-        Some(p) => *p = state,
-        _ => ()
+    if let Some(p) = model.buf.get_mut(ROW_P * y as usize + x as usize - 1) {
+        *p = state
     }
 
-    match model.buf.get_mut(ROW_P * (y as usize + 1) + x as usize) {
-        Some(p) => *p = state,
-        _ => ()
+    if let Some(p) = model.buf.get_mut(ROW_P * (y as usize + 1) + x as usize) {
+        *p = state
     }
 
-    match model.buf.get_mut(ROW_P * (y as usize - 1) + x as usize) {
-        Some(p) => *p = state,
-        _ => ()
+    if let Some(p) = model.buf.get_mut(ROW_P * (y as usize - 1) + x as usize) {
+        *p = state
     }
 }
 
@@ -146,9 +141,9 @@ fn reset_buffer(_: &App, model: &mut Model, key: Key) {
             let buf = model.buf.map(|n| n as f32);
             let buf = Vector::from_arr(buf);
 
-            let digit = model.net.forward_prop(&buf).hot();
-
-            println!("predicted digit: {}", digit);
+            for (i, prediction) in model.net.top_k(&buf, 3).iter().enumerate() {
+                println!("{}. digit {} with {:.1}%", i+1, prediction.class, 100. * prediction.prob);
+            }
         }
         _ => ()
     }
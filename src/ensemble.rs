@@ -0,0 +1,207 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use serde_derive::{Serialize, Deserialize};
+
+use crate::classify::Classifier;
+use crate::error::{NetError, Result};
+use crate::format;
+use crate::linalg::*;
+use crate::model::Model;
+use crate::net::Net;
+
+/// Enumerated way of combining member predictions
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Voting {
+    // mean of the member probabilities
+    #[default]
+    Average,
+
+    // mean of the member probabilities scaled by their weights
+    Weighted,
+
+    // share of members predicting each class, scaled by their weights
+    Majority
+}
+
+/// Group of trained networks, possibly of different architectures,
+/// predicting together as a single classifier
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Ensemble {
+    members: Vec<Model>,
+
+    // vote weight of each member
+    weights: Vec<f32>,
+
+    voting: Voting
+}
+
+impl Ensemble {
+    pub fn new(voting: Voting) -> Self {
+        Self {
+            voting,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a network of unit weight
+    pub fn with_net<const L: usize>(&mut self, net: &Net<L>) -> &mut Self {
+        self.with_model(Model::from(net), 1.)
+    }
+
+    /// Adds a network with its vote weight
+    pub fn with_weighted_net<const L: usize>(&mut self, net: &Net<L>, weight: f32) -> &mut Self {
+        self.with_model(Model::from(net), weight)
+    }
+
+    pub fn with_model(&mut self, model: Model, weight: f32) -> &mut Self {
+        self.try_add(model, weight)
+            .unwrap_or_else(|err| panic!("{}", err));
+
+        self
+    }
+
+    /// Adds a model, failing if its inputs or outputs don't match the members'
+    pub fn try_add(&mut self, model: Model, weight: f32) -> Result<()> {
+        if let Some(first) = self.members.first() {
+            check_ends(first, &model)?;
+        }
+
+        self.members.push(model);
+        self.weights.push(weight.max(0.));
+
+        Ok(())
+    }
+
+    pub fn with_voting(&mut self, voting: Voting) -> &mut Self {
+        self.voting = voting;
+        self
+    }
+
+    pub fn voting(&self) -> Voting {
+        self.voting
+    }
+
+    pub fn members(&self) -> &[Model] {
+        &self.members
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    /// Returns the combined class probabilities of the members
+    pub fn forward_prop(&self, input: &Vector) -> Vector {
+        match self.try_forward_prop(input) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    pub fn try_forward_prop(&self, input: &Vector) -> Result<Vector> {
+        let first = self.members.first().ok_or(NetError::EmptyEnsemble)?;
+        let classes = first.form()[first.form().len()-1];
+
+        let mut combined = Vector::from_zeros(classes);
+        let mut total = 0.;
+
+        for (member, weight) in self.members.iter().zip(&self.weights) {
+            let weight = match self.voting {
+                Voting::Average => 1.,
+                Voting::Weighted | Voting::Majority => *weight
+            };
+
+            let probs = member.try_probabilities(input)?;
+
+            if self.voting == Voting::Majority {
+                combined[probs.hot()] += weight;
+            }
+            else {
+                combined.add_eq(&probs.scale(weight));
+            }
+
+            total += weight;
+        }
+
+        if total > 0. {
+            combined.scale_eq(1. / total);
+        }
+
+        Ok(combined)
+    }
+
+    /// Atomically writes the versioned ensemble with all its members to path
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_versioned(self, path)
+    }
+
+    /// Writes the versioned ensemble with all its members to writer
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<()> {
+        format::write_versioned(self, writer)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Self {
+        Self::try_from_file(path)
+            .unwrap_or_else(|err| panic!("couldn't load ensemble: {}", err))
+    }
+
+    pub fn try_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_from(format::open(path)?)
+    }
+
+    pub fn read_from<R: Read>(reader: R) -> Result<Self> {
+        let bytes = format::read_all(reader)?;
+
+        let ensemble: Self = format::from_versioned(&bytes)?;
+        ensemble.check_members()?;
+
+        Ok(ensemble)
+    }
+
+    /// Fails if a member is malformed or doesn't match the others
+    fn check_members(&self) -> Result<()> {
+        if self.weights.len() != self.members.len() {
+            return Err(NetError::Length { inputs: self.members.len(), targets: self.weights.len() })
+        }
+
+        for member in self.members.iter() {
+            member.check_form()?;
+            check_ends(&self.members[0], member)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Classifier for Ensemble {
+    fn try_output(&self, input: &Vector) -> Result<Vector> {
+        self.try_forward_prop(input)
+    }
+
+    /// The combined output already holds class probabilities
+    fn normalize_output(&self, out: &Vector) -> Vector {
+        out.clone()
+    }
+}
+
+/// Fails if the models differ in input or output size
+fn check_ends(lhs: &Model, rhs: &Model) -> Result<()> {
+    let ends = |model: &Model| (model.form()[0], model.form()[model.form().len()-1]);
+
+    let (inputs, outputs) = ends(lhs);
+    let (rhs_inputs, rhs_outputs) = ends(rhs);
+
+    if inputs != rhs_inputs {
+        return Err(NetError::Shape { expected: (inputs, 1), found: (rhs_inputs, 1) })
+    }
+
+    if outputs != rhs_outputs {
+        return Err(NetError::Shape { expected: (outputs, 1), found: (rhs_outputs, 1) })
+    }
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use std::fmt;
+use std::io;
+
+/// Recoverable failures of the network's public entry points
+#[derive(Debug)]
+pub enum NetError {
+    // network form with fewer than three layers
+    TooFewLayers(usize),
+
+    // network form that doesn't match the const layer count
+    LayerCount { expected: usize, found: usize },
+
+    // input or target of the wrong shape
+    Shape { expected: (usize, usize), found: (usize, usize) },
+
+    // linear algebra operation on unmatched dimensions
+    Dimension { op: &'static str, lhs: (usize, usize), rhs: (usize, usize) },
+
+    // deserialized matrix whose buffer doesn't fill its shape
+    Buffer { shape: (usize, usize), len: usize },
+
+    // unequal amounts of input and target data
+    Length { inputs: usize, targets: usize },
+
+    // model file couldn't be read or written
+    Io(io::Error),
+
+    // model couldn't be (de)serialized
+    Format(serde_json::Error),
+
+    // model file of an unsupported format version
+    Version { found: u32, supported: u32 },
+
+    // malformed binary model file
+    Corrupt(&'static str),
+
+    // ensemble without any members
+    EmptyEnsemble,
+
+    // quantization without calibration samples
+    EmptyCalibration,
+
+    // hyper parameters that can't be trained with
+    Config(&'static str),
+
+    // network surgery on an invalid layer or neuron
+    Surgery(&'static str),
+
+    // hyper parameter search without candidates
+    Search(&'static str),
+
+    // cross-validation folds that don't divide the data
+    FoldCount { folds: usize, samples: usize },
+
+    // attention heads that don't split the features evenly
+    Heads { dim: usize, heads: usize },
+}
+
+pub type Result<T> = std::result::Result<T, NetError>;
+
+impl fmt::Display for NetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetError::TooFewLayers(found) =>
+                write!(f, "expected at least 3 layers, found {}", found),
+            NetError::LayerCount { expected, found } =>
+                write!(f, "expected {} layers, found {}", expected, found),
+            NetError::Shape { expected, found } =>
+                write!(f, "expected data of shape {:?}, found {:?}", expected, found),
+            NetError::Dimension { op, lhs, rhs } =>
+                write!(f, "unmatched '{}' dimensions {:?} | {:?}", op, lhs, rhs),
+            NetError::Buffer { shape, len } =>
+                write!(f, "buffer of {} values doesn't match shape {:?}", len, shape),
+            NetError::Length { inputs, targets } =>
+                write!(f, "unequal amounts of input ({}) and output ({}) data", inputs, targets),
+            NetError::Io(err) =>
+                write!(f, "model i/o failed: {}", err),
+            NetError::Format(err) =>
+                write!(f, "invalid model format: {}", err),
+            NetError::Version { found, supported } =>
+                write!(f, "unsupported model format version {}, expected version {}", found, supported),
+            NetError::Corrupt(reason) =>
+                write!(f, "corrupt binary model: {}", reason),
+            NetError::EmptyEnsemble =>
+                write!(f, "ensemble has no members"),
+            NetError::EmptyCalibration =>
+                write!(f, "quantization needs at least one calibration sample"),
+            NetError::Config(reason) =>
+                write!(f, "invalid hyper parameters: {}", reason),
+            NetError::Surgery(reason) =>
+                write!(f, "invalid network surgery: {}", reason),
+            NetError::Search(reason) =>
+                write!(f, "invalid hyper parameter search: {}", reason),
+            NetError::FoldCount { folds, samples } =>
+                write!(f, "cannot split {} samples into {} folds", samples, folds),
+            NetError::Heads { dim, heads } =>
+                write!(f, "cannot split {} features across {} heads", dim, heads),
+        }
+    }
+}
+
+impl std::error::Error for NetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            NetError::Io(err) => Some(err),
+            NetError::Format(err) => Some(err),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for NetError {
+    fn from(err: io::Error) -> Self {
+        NetError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for NetError {
+    fn from(err: serde_json::Error) -> Self {
+        NetError::Format(err)
+    }
+}
@@ -0,0 +1,188 @@
+use std::fmt;
+use std::path::Path;
+
+use serde_derive::{Serialize, Deserialize};
+
+use crate::error::{NetError, Result};
+use crate::format;
+use crate::linalg::*;
+
+/// Precision, recall and F1 score of a class or an average of classes
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Scores {
+    pub precision: f32,
+    pub recall: f32,
+    pub f1: f32
+}
+
+impl Scores {
+    pub(crate) fn from_counts(tp: usize, fp: usize, fn_: usize) -> Self {
+        let precision = ratio(tp, tp + fp);
+        let recall = ratio(tp, tp + fn_);
+
+        Self { precision, recall, f1: f1(precision, recall) }
+    }
+}
+
+/// Scores of a single class with its number of samples
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ClassScores {
+    pub class: usize,
+    pub scores: Scores,
+    pub support: usize
+}
+
+/// Classification report of a network over a labelled data set
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Evaluation {
+    // evaluated samples
+    pub samples: usize,
+
+    // sample counts indexed as [actual][predicted]
+    pub confusion: Vec<Vec<usize>>,
+
+    // per-class scores
+    pub classes: Vec<ClassScores>,
+
+    // unweighted mean of the per-class scores
+    pub macro_avg: Scores,
+
+    // scores of the summed per-class counts
+    pub micro_avg: Scores,
+
+    // top-1 through top-k accuracy
+    pub top_k: Vec<f32>
+}
+
+impl Evaluation {
+    /// Builds the report from class probabilities and one-hot targets
+    pub fn from_probabilities(probs: &[Vector], targets: &[Vector], k: usize) -> Self {
+        Self::try_from_probabilities(probs, targets, k)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_from_probabilities(probs: &[Vector], targets: &[Vector], k: usize) -> Result<Self> {
+        if probs.len() != targets.len() {
+            return Err(NetError::Length { inputs: probs.len(), targets: targets.len() })
+        }
+
+        let classes = probs.first().map_or(0, |p| p.row());
+        let k = k.clamp(1, classes.max(1));
+
+        let mut confusion = vec![vec![0; classes]; classes];
+        let mut hits = vec![0; k];
+
+        for (prob, target) in probs.iter().zip(targets) {
+            if prob.shape() != (classes, 1) {
+                return Err(NetError::Shape { expected: (classes, 1), found: prob.shape() })
+            }
+            if target.shape() != (classes, 1) {
+                return Err(NetError::Shape { expected: (classes, 1), found: target.shape() })
+            }
+
+            let actual = target.hot();
+            confusion[actual][prob.hot()] += 1;
+
+            // classes ranked above the actual one, ties go to the lower
+            // class like `hot`, so a top-1 hit is a confusion matrix hit
+            let rank = prob
+                .buf()
+                .iter()
+                .enumerate()
+                .filter(|(class, p)| **p > prob[actual] || (**p == prob[actual] && *class < actual))
+                .count();
+
+            for hit in hits.iter_mut().skip(rank) {
+                *hit += 1;
+            }
+        }
+
+        let mut total = (0, 0, 0);
+        let mut class_scores = Vec::with_capacity(classes);
+
+        for class in 0..classes {
+            let tp = confusion[class][class];
+            let support: usize = confusion[class].iter().sum();
+            let predicted: usize = confusion.iter().map(|row| row[class]).sum();
+
+            let (fp, fn_) = (predicted - tp, support - tp);
+
+            total.0 += tp;
+            total.1 += fp;
+            total.2 += fn_;
+
+            class_scores.push(ClassScores { class, scores: Scores::from_counts(tp, fp, fn_), support });
+        }
+
+        let mean = |score: fn(&Scores) -> f32| {
+            class_scores.iter().map(|c| score(&c.scores)).sum::<f32>() / classes.max(1) as f32
+        };
+
+        let macro_avg = Scores {
+            precision: mean(|s| s.precision),
+            recall: mean(|s| s.recall),
+            f1: mean(|s| s.f1)
+        };
+
+        Ok(Self {
+            samples: probs.len(),
+            confusion,
+            classes: class_scores,
+            macro_avg,
+            micro_avg: Scores::from_counts(total.0, total.1, total.2),
+            top_k: hits.iter().map(|hit| ratio(*hit, probs.len())).collect()
+        })
+    }
+
+    /// Returns the top-1 accuracy
+    pub fn accuracy(&self) -> f32 {
+        self.top_k.first().copied().unwrap_or_default()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        format::to_json(self)
+    }
+
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_json(self, path)
+    }
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>9} {:>9} {:>9} {:>9} {:>9}", "class", "precision", "recall", "f1", "support")?;
+
+        for class in self.classes.iter() {
+            let s = class.scores;
+            writeln!(f, "{:>9} {:>9.3} {:>9.3} {:>9.3} {:>9}", class.class, s.precision, s.recall, s.f1, class.support)?;
+        }
+
+        writeln!(f)?;
+        for (name, s) in [("macro", self.macro_avg), ("micro", self.micro_avg)] {
+            writeln!(f, "{:>9} {:>9.3} {:>9.3} {:>9.3} {:>9}", name, s.precision, s.recall, s.f1, self.samples)?;
+        }
+
+        writeln!(f)?;
+        for (k, acc) in self.top_k.iter().enumerate() {
+            writeln!(f, "top-{} accuracy: {:.3}", k+1, acc)?;
+        }
+
+        writeln!(f, "\nconfusion (actual \\ predicted):")?;
+        for row in self.confusion.iter() {
+            for n in row {
+                write!(f, "{:>6}", n)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn ratio(n: usize, d: usize) -> f32 {
+    if d == 0 { 0. } else { n as f32 / d as f32 }
+}
+
+fn f1(precision: f32, recall: f32) -> f32 {
+    if precision + recall == 0. { 0. } else { 2. * precision * recall / (precision + recall) }
+}
@@ -0,0 +1,498 @@
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::error::{NetError, Result};
+use crate::linalg::*;
+
+/// Current model file format version
+///
+/// VERSION HISTORY:
+///
+/// 0    bare serialized model without an envelope
+/// 1    model wrapped as { "version": 1, "model": ... }
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Serializes model wrapped in a versioned envelope
+pub fn to_versioned<T: Serialize>(model: &T) -> Result<String> {
+    #[derive(serde_derive::Serialize)]
+    struct Versioned<'a, T> {
+        version: u32,
+        model: &'a T
+    }
+
+    let json = serde_json::to_string(&Versioned { version: FORMAT_VERSION, model })?;
+    Ok(json)
+}
+
+/// Deserializes model of any known format version, migrating it to the current one
+pub fn from_versioned<T: DeserializeOwned>(json: &[u8]) -> Result<T> {
+    let value: Value = serde_json::from_slice(json)?;
+
+    let model = match value {
+        Value::Object(mut obj) if obj.contains_key("version") && obj.contains_key("model") => {
+            let version = obj["version"]
+                .as_u64()
+                .map_or(u32::MAX, |v| v.min(u32::MAX as u64) as u32);
+
+            migrate(obj.remove("model").unwrap_or(Value::Null), version)?
+        }
+        value => migrate(value, 0)?
+    };
+
+    Ok(serde_json::from_value(model)?)
+}
+
+/// Upgrades a serialized model from version to `FORMAT_VERSION`
+fn migrate(model: Value, version: u32) -> Result<Value> {
+    match version {
+        // fields added since are filled by their serde defaults
+        0 => Ok(model),
+        FORMAT_VERSION => Ok(model),
+        found => Err(NetError::Version { found, supported: FORMAT_VERSION })
+    }
+}
+
+/// Counter distinguishing the temporary files of concurrent saves
+static TMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Writes bytes to path through a temporary file that is renamed
+/// into place, so a crash never leaves a truncated file behind
+pub fn write_atomic<P: AsRef<Path>>(path: P, bytes: &[u8]) -> Result<()> {
+    let path = path.as_ref();
+
+    let mut name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "model path has no file name"))?
+        .to_os_string();
+
+    // unique per process and save, so concurrent saves never share a file
+    let id = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    name.push(format!(".{}.{}.tmp", std::process::id(), id));
+    let tmp = path.with_file_name(name);
+
+    let result = File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&tmp, path));
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+
+    Ok(result?)
+}
+
+/// Atomically writes the versioned model to path
+pub fn save_versioned<T: Serialize, P: AsRef<Path>>(model: &T, path: P) -> Result<()> {
+    write_atomic(path, to_versioned(model)?.as_bytes())
+}
+
+/// Writes the versioned model to writer
+pub fn write_versioned<T: Serialize, W: Write>(model: &T, mut writer: W) -> Result<()> {
+    writer.write_all(to_versioned(model)?.as_bytes())?;
+    Ok(())
+}
+
+/// Serializes a report or other value as json
+pub fn to_json<T: Serialize>(value: &T) -> Result<String> {
+    Ok(serde_json::to_string(value)?)
+}
+
+/// Atomically writes value as json to path
+pub fn save_json<T: Serialize, P: AsRef<Path>>(value: &T, path: P) -> Result<()> {
+    write_atomic(path, to_json(value)?.as_bytes())
+}
+
+/// Opens path for buffered reading
+pub fn open<P: AsRef<Path>>(path: P) -> Result<BufReader<File>> {
+    Ok(BufReader::new(File::open(path)?))
+}
+
+/// Reads the remaining bytes of reader
+pub fn read_all<R: Read>(mut reader: R) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+
+    Ok(bytes)
+}
+
+/// Fails unless form has a hidden layer and a weight and bias
+/// of matching shape between every two of its layers
+pub(crate) fn check_form<W, B>(form: &[usize], weights: W, biases: B) -> Result<()>
+where
+    W: ExactSizeIterator<Item = (usize, usize)>,
+    B: ExactSizeIterator<Item = (usize, usize)>
+{
+    if form.len() <= 2 {
+        return Err(NetError::TooFewLayers(form.len()))
+    }
+
+    check_shapes(weights, form.len()-1, |l| (form[l+1], form[l]))?;
+    check_shapes(biases, form.len()-1, |l| (form[l+1], 1))
+}
+
+/// Fails unless there are as many shapes as layers, each the expected one
+pub(crate) fn check_shapes<I, F>(shapes: I, layers: usize, expected: F) -> Result<()>
+where
+    I: ExactSizeIterator<Item = (usize, usize)>,
+    F: Fn(usize) -> (usize, usize)
+{
+    if shapes.len() != layers {
+        return Err(NetError::LayerCount { expected: layers, found: shapes.len() })
+    }
+
+    for (l, found) in shapes.enumerate() {
+        if found != expected(l) {
+            return Err(NetError::Shape { expected: expected(l), found })
+        }
+    }
+
+    Ok(())
+}
+
+/// BINARY FORMAT:
+///
+/// VERSION HISTORY:
+///
+/// 1    layer parameters only
+/// 2    followed by the pruning masks, if any
+///
+/// all values are little-endian
+///
+/// [offset]   [type]           [description]
+/// 0000       [u8; 4]          magic "NETB"
+/// 0004       u32              binary format version
+/// 0008       u8               dtype (0 = f32)
+/// 0009       u32              # layers
+/// 0013       u32              layer size
+/// ...
+/// xxxx       u32              # meta bytes
+/// xxxx       [u8]             meta data as JSON
+/// xxxx       f32              weight, layer by layer in row-major order
+/// ...
+/// xxxx       f32              bias, layer by layer
+/// ...
+/// xxxx       u8               masks (0 = none, 1 = masked)
+/// xxxx       [u8]             mask bits, layer by layer in row-major order,
+///                             least significant first and padded to a byte
+const BINARY_MAGIC: &[u8; 4] = b"NETB";
+const BINARY_VERSION: u32 = 2;
+const DTYPE_F32: u8 = 0;
+
+/// Layer parameters and JSON meta data of a binary model
+pub struct Binary {
+    pub form: Vec<usize>,
+    pub meta: Vec<u8>,
+    pub weights: Vec<Matrix>,
+    pub biases: Vec<Vector>,
+    pub masks: Option<Vec<Matrix>>
+}
+
+/// Returns whether bytes start with the binary magic number
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes.starts_with(BINARY_MAGIC)
+}
+
+/// Encodes the layer parameters of `form` and their pruning masks into the binary format
+pub fn to_binary(form: &[usize], meta: &[u8], weights: &[Matrix], biases: &[Vector], masks: Option<&[Matrix]>) -> Vec<u8> {
+    let params: usize = (1..form.len()).map(|l| form[l] * form[l-1] + form[l]).sum();
+    let mut bytes = Vec::with_capacity(17 + 4 * form.len() + meta.len() + 4 * params);
+
+    bytes.extend_from_slice(BINARY_MAGIC);
+    bytes.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+    bytes.push(DTYPE_F32);
+    bytes.extend_from_slice(&(form.len() as u32).to_le_bytes());
+
+    for size in form {
+        bytes.extend_from_slice(&(*size as u32).to_le_bytes());
+    }
+
+    bytes.extend_from_slice(&(meta.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(meta);
+
+    let weights = weights.iter().flat_map(|w| w.buf().iter());
+    let biases = biases.iter().flat_map(|b| b.buf().iter());
+
+    for n in weights.chain(biases) {
+        bytes.extend_from_slice(&n.to_le_bytes());
+    }
+
+    bytes.push(masks.is_some() as u8);
+
+    for mask in masks.into_iter().flatten() {
+        for bits in mask.buf().chunks(8) {
+            bytes.push(bits.iter().rev().fold(0, |byte, n| byte << 1 | (*n != 0.) as u8));
+        }
+    }
+
+    bytes
+}
+
+/// Decodes a binary model, validating its header and buffer sizes
+pub fn from_binary(bytes: &[u8]) -> Result<Binary> {
+    let mut reader = ByteReader::new(bytes);
+
+    if reader.take(4)? != BINARY_MAGIC {
+        return Err(NetError::Corrupt("missing magic number"))
+    }
+
+    let version = reader.u32()?;
+    if version == 0 || version > BINARY_VERSION {
+        return Err(NetError::Version { found: version, supported: BINARY_VERSION })
+    }
+
+    if reader.take(1)?[0] != DTYPE_F32 {
+        return Err(NetError::Corrupt("unsupported dtype"))
+    }
+
+    let layers = reader.u32()? as usize;
+    if layers < 2 {
+        return Err(NetError::TooFewLayers(layers))
+    }
+
+    let form = (0..layers)
+        .map(|_| reader.u32().map(|n| n as usize))
+        .collect::<Result<Vec<_>>>()?;
+
+    let meta_len = reader.u32()? as usize;
+    let meta = reader.take(meta_len)?.to_vec();
+
+    let weights = (1..layers)
+        .map(|l| Ok(Matrix::from_buf((form[l], form[l-1]), reader.f32s(form[l] * form[l-1])?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let biases = (1..layers)
+        .map(|l| Ok(Vector::from_buf(form[l], reader.f32s(form[l])?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    // version 1 files end after the parameters
+    let masked = version >= 2 && match reader.take(1)?[0] {
+        0 => false,
+        1 => true,
+        _ => return Err(NetError::Corrupt("invalid mask flag"))
+    };
+
+    let masks = if masked {
+        let masks = (1..layers)
+            .map(|l| {
+                let size = form[l] * form[l-1];
+                let bits = reader.take(size.div_ceil(8))?;
+
+                let buf = (0..size).map(|i| (bits[i / 8] >> (i % 8) & 1) as f32).collect();
+                Ok(Matrix::from_buf((form[l], form[l-1]), buf))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Some(masks)
+    }
+    else {
+        None
+    };
+
+    if !reader.is_done() {
+        return Err(NetError::Corrupt("trailing bytes after parameters"))
+    }
+
+    Ok(Binary { form, meta, weights, biases, masks })
+}
+
+/// Bounds-checked little-endian reader over binary model bytes
+pub(crate) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    /// Returns whether every byte has been read
+    pub(crate) fn is_done(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(NetError::Corrupt("unexpected end of file"))?;
+
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+
+        Ok(bytes)
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        let buf = self.take(4)?;
+        Ok(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]))
+    }
+
+    pub(crate) fn f32(&mut self) -> Result<f32> {
+        Ok(f32::from_bits(self.u32()?))
+    }
+
+    pub(crate) fn i32s(&mut self, len: usize) -> Result<Vec<i32>> {
+        let bytes = self.take(len.checked_mul(4).ok_or(NetError::Corrupt("layer too large"))?)?;
+
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|buf| i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]))
+            .collect())
+    }
+
+    pub(crate) fn i8s(&mut self, len: usize) -> Result<Vec<i8>> {
+        Ok(self.take(len)?.iter().map(|b| *b as i8).collect())
+    }
+
+    pub(crate) fn f32s(&mut self, len: usize) -> Result<Vec<f32>> {
+        let bytes = self.take(len.checked_mul(4).ok_or(NetError::Corrupt("layer too large"))?)?;
+
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|buf| f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Model;
+    use crate::net::Net;
+
+    fn json(net: &Net<3>) -> Value {
+        serde_json::from_str(&to_versioned(net).unwrap()).unwrap()
+    }
+
+    fn same_params(a: &Net<3>, b: &Net<3>) -> bool {
+        a.weights.buf == b.weights.buf && a.biases.buf == b.biases.buf
+    }
+
+    #[test]
+    fn versioned_json_round_trips() {
+        let net = Net::new([3, 4, 2]).build();
+
+        let mut bytes = Vec::new();
+        net.write_to(&mut bytes).unwrap();
+
+        assert_eq!(json(&net)["version"], FORMAT_VERSION);
+        assert!(same_params(&net, &Net::read_from(bytes.as_slice()).unwrap()));
+    }
+
+    #[test]
+    fn bare_version_0_json_loads() {
+        let net = Net::new([3, 4, 2]).build();
+        let bytes = serde_json::to_vec(&net).unwrap();
+
+        assert!(same_params(&net, &Net::read_from(bytes.as_slice()).unwrap()));
+    }
+
+    #[test]
+    fn newer_version_fails_to_load() {
+        let mut json = json(&Net::new([3, 4, 2]).build());
+        json["version"] = Value::from(FORMAT_VERSION + 1);
+
+        let bytes = serde_json::to_vec(&json).unwrap();
+
+        assert!(matches!(
+            Net::<3>::read_from(bytes.as_slice()),
+            Err(NetError::Version { found, supported: FORMAT_VERSION }) if found == FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn cut_short_json_fails_to_load() {
+        let json = to_versioned(&Net::new([3, 4, 2]).build()).unwrap();
+        let bytes = &json.as_bytes()[..json.len() / 2];
+
+        assert!(matches!(Net::<3>::read_from(bytes), Err(NetError::Format(_))));
+    }
+
+    #[test]
+    fn truncated_buffer_fails_to_load() {
+        let mut json = json(&Net::new([3, 4, 2]).build());
+        json["model"]["weights"]["buf"][0]["buf"] = Value::from(vec![0.5]);
+
+        let bytes = serde_json::to_vec(&json).unwrap();
+
+        assert!(matches!(Net::<3>::read_from(bytes.as_slice()), Err(NetError::Format(_))));
+        assert!(matches!(Model::read_from(bytes.as_slice()), Err(NetError::Format(_))));
+    }
+
+    #[test]
+    fn binary_round_trips() {
+        let net = Net::new([3, 4, 2]).build();
+
+        let mut bytes = Vec::new();
+        net.write_binary_to(&mut bytes).unwrap();
+
+        assert!(is_binary(&bytes));
+        assert!(same_params(&net, &Net::read_from(bytes.as_slice()).unwrap()));
+
+        let model = Model::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(model.form(), &[3, 4, 2]);
+    }
+
+    #[test]
+    fn version_1_binary_loads_without_masks() {
+        let net = Net::new([3, 4, 2]).build();
+
+        let mut bytes = Vec::new();
+        net.write_binary_to(&mut bytes).unwrap();
+
+        // version 1 files lack the trailing mask flag
+        bytes.pop();
+        bytes[4..8].copy_from_slice(&1_u32.to_le_bytes());
+
+        let loaded = Net::read_from(bytes.as_slice()).unwrap();
+        assert!(same_params(&net, &loaded) && loaded.masks.is_none());
+    }
+
+    #[test]
+    fn corrupt_binary_fails_to_load() {
+        let mut bytes = Vec::new();
+        Net::new([3, 4, 2]).build().write_binary_to(&mut bytes).unwrap();
+
+        let cut = &bytes[..bytes.len() - 1];
+        assert!(matches!(Net::<3>::read_from(cut), Err(NetError::Corrupt(_))));
+
+        let mut trailing = bytes.clone();
+        trailing.push(0);
+        assert!(matches!(Net::<3>::read_from(trailing.as_slice()), Err(NetError::Corrupt(_))));
+
+        let mut dtype = bytes.clone();
+        dtype[8] = 1;
+        assert!(matches!(Net::<3>::read_from(dtype.as_slice()), Err(NetError::Corrupt(_))));
+
+        let mut version = bytes;
+        version[4] = 0;
+        assert!(matches!(Net::<3>::read_from(version.as_slice()), Err(NetError::Version { found: 0, .. })));
+    }
+
+    #[test]
+    fn save_leaves_only_the_model_file() {
+        let dir = std::env::temp_dir().join(format!("net-rs-format-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let net = Net::new([3, 4, 2]).build();
+        net.save_to(dir.join("net.json")).unwrap();
+        net.save_to(dir.join("net.json")).unwrap();
+
+        let loaded = Net::try_from_file(dir.join("net.json"));
+        let files = std::fs::read_dir(&dir).unwrap().count();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(same_params(&net, &loaded.unwrap()));
+        assert_eq!(files, 1);
+    }
+}
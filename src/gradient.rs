@@ -0,0 +1,186 @@
+use serde_derive::{Serialize, Deserialize};
+
+use crate::linalg::*;
+use crate::net::Net;
+
+/// Default finite difference step
+pub(crate) const CHECK_EPSILON: f32 = 1e-2;
+/// Lower bound of the relative error denominator
+const CHECK_FLOOR: f32 = 1e-3;
+
+/// Gradient clipping applied to each averaged batch gradient
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum Clip {
+    // clamps every element to [-n, n]
+    Value(f32),
+    
+    // rescales the gradient to a global L2 norm of at most n
+    Norm(f32)
+}
+
+impl Clip {
+    /// Returns whether the bound is positive, rejecting NaN
+    pub fn is_valid(&self) -> bool {
+        match self {
+            Clip::Value(max) | Clip::Norm(max) => *max > 0.
+        }
+    }
+}
+
+/// Largest relative error between numerical and
+/// back-propagated gradients of a single layer
+#[derive(Clone, Copy, Debug)]
+pub struct LayerCheck {
+    pub weights: f32,
+    pub biases: f32
+}
+
+impl LayerCheck {
+    pub fn max(&self) -> f32 {
+        self.weights.max(self.biases)
+    }
+}
+
+impl<const L: usize> Net<L> {
+    /// Returns the global L2 norm of the accumulated weight and bias errors
+    pub fn gradient_norm(&self) -> f32 {
+        let weights = self.acc_w_err.buf.iter().flat_map(|m| m.buf().iter());
+        let biases = self.acc_err.buf.iter().flat_map(|v| v.buf().iter());
+
+        weights
+            .chain(biases)
+            .map(|n| n * n)
+            .sum::<f32>()
+            .sqrt()
+    }
+
+    /// Clips the accumulated errors of `sample_size` samples by the configured
+    /// `Clip`, returning the norm of the averaged gradient before clipping
+    pub(crate) fn clip_gradient(&mut self, sample_size: usize, clip: Option<Clip>) -> f32 {
+        let samples = sample_size as f32;
+        let norm = self.gradient_norm() / samples;
+
+        match clip {
+            Some(Clip::Value(max)) => {
+                // accumulators hold sums, so bound by max * samples
+                let max = max * samples;
+
+                for l in 0..L-1 {
+                    self.acc_w_err[l].map_eq(|n| n.clamp(-max, max));
+                    self.acc_err[l].map_eq(|n| n.clamp(-max, max));
+                }
+            }
+            Some(Clip::Norm(max)) if norm > max => {
+                for l in 0..L-1 {
+                    self.acc_w_err[l].scale_eq(max / norm);
+                    self.acc_err[l].scale_eq(max / norm);
+                }
+            }
+            _ => ()
+        }
+
+        norm
+    }
+
+    /// Compares `back_prop` against central finite differences of every weight and bias
+    pub fn gradient_check(&mut self, input: &Vector, target: &Vector) -> Vec<LayerCheck> {
+        self.gradient_check_with(input, target, CHECK_EPSILON)
+    }
+
+    /// Compares `back_prop` against central finite differences with step `epsilon`
+    pub fn gradient_check_with(&mut self, input: &Vector, target: &Vector, epsilon: f32) -> Vec<LayerCheck> {
+        self.back_prop(input, target);
+
+        // errors hold the negative cost gradient
+        let w_err = self.w_err.clone();
+        let err = self.err.clone();
+
+        (0..L-1)
+            .map(|l| {
+                let weights = (0..w_err[l].buf().len())
+                    .map(|i| {
+                        let numeric = self.numeric_gradient(input, target, epsilon, |net| &mut net.weights[l].buf_mut()[i]);
+                        relative_error(numeric, -w_err[l].buf()[i])
+                    })
+                    .fold(0., f32::max);
+
+                let biases = (0..err[l].buf().len())
+                    .map(|i| {
+                        let numeric = self.numeric_gradient(input, target, epsilon, |net| &mut net.biases[l].buf_mut()[i]);
+                        relative_error(numeric, -err[l].buf()[i])
+                    })
+                    .fold(0., f32::max);
+
+                LayerCheck { weights, biases }
+            })
+            .collect()
+    }
+
+    /// Returns ( cost(p + e) - cost(p - e) ) / 2e of the selected parameter
+    fn numeric_gradient<F>(&mut self, input: &Vector, target: &Vector, epsilon: f32, param: F) -> f32
+    where
+        F: Fn(&mut Self) -> &mut f32
+    {
+        let value = *param(self);
+
+        *param(self) = value + epsilon;
+        self.forward_prop(input);
+        let plus = self.output_loss(target);
+
+        *param(self) = value - epsilon;
+        self.forward_prop(input);
+        let minus = self.output_loss(target);
+
+        *param(self) = value;
+
+        (plus - minus) / (2. * epsilon)
+    }
+}
+
+fn relative_error(numeric: f32, analytic: f32) -> f32 {
+    (numeric - analytic).abs() / (numeric.abs() + analytic.abs()).max(CHECK_FLOOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cost::Cost;
+    use crate::step::{Activation, Output};
+
+    const TOLERANCE: f32 = 5e-2;
+
+    #[test]
+    fn back_prop_matches_finite_differences() {
+        let acts = [Activation::Sig, Activation::Tanh, Activation::Lin];
+        let outs = [Output::Act, Output::Softmax, Output::Sigmoid];
+        let costs = [Cost::Quad, Cost::BinCross];
+
+        let input = Vector::from_buf(3, vec![0.3, -0.6, 0.9]);
+        let target = Vector::from_buf(2, vec![0.2, 0.8]);
+
+        for act in acts {
+            for out in outs {
+                for cost in costs {
+                    // cross-entropy clamps outputs outside of (0, 1), which
+                    // only tanh and linear step functions produce
+                    if cost == Cost::BinCross && out == Output::Act && !matches!(act, Activation::Sig) {
+                        continue
+                    }
+
+                    let mut net = Net::new([3, 4, 2])
+                        .with_act(act)
+                        .with_output(out)
+                        .with_cost(cost)
+                        .build();
+
+                    for (l, check) in net.gradient_check(&input, &target).iter().enumerate() {
+                        assert!(
+                            check.max() < TOLERANCE,
+                            "{:?} {:?} {:?} layer {}: {:?}", act, out, cost, l, check
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
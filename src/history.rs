@@ -0,0 +1,98 @@
+use std::path::Path;
+
+use serde_derive::{Serialize, Deserialize};
+
+use crate::callback::Metrics;
+use crate::error::Result;
+use crate::format;
+use crate::regression::RegressionScores;
+
+/// CSV columns of each epoch's metrics
+pub const CSV_HEADER: &str = "epoch,loss,accuracy,valid_loss,valid_accuracy,grad_norm,learn_rate,time,\
+mse,mae,rmse,r2,valid_mse,valid_mae,valid_rmse,valid_r2";
+
+/// Per-epoch record of a training run
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct History {
+    pub epochs: Vec<Metrics>
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, metrics: Metrics) {
+        self.epochs.push(metrics);
+    }
+
+    pub fn len(&self) -> usize {
+        self.epochs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.epochs.is_empty()
+    }
+
+    pub fn last(&self) -> Option<&Metrics> {
+        self.epochs.last()
+    }
+
+    /// Returns the epoch with the lowest validation (or training) loss
+    pub fn best(&self) -> Option<&Metrics> {
+        self.epochs
+            .iter()
+            .min_by(|a, b| a.monitored_loss().total_cmp(&b.monitored_loss()))
+    }
+
+    /// Returns the total wall time in seconds
+    pub fn time(&self) -> f32 {
+        self.epochs.iter().map(|m| m.time).sum()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        format::to_json(self)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{}\n", CSV_HEADER);
+
+        for metrics in self.epochs.iter() {
+            csv.push_str(&csv_row(metrics));
+            csv.push('\n');
+        }
+
+        csv
+    }
+
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_json(self, path)
+    }
+
+    pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::write_atomic(path, self.to_csv().as_bytes())
+    }
+}
+
+/// Returns the metrics as a CSV row matching `CSV_HEADER`
+pub fn csv_row(metrics: &Metrics) -> String {
+    let optional = |n: Option<f32>| n.map(|n| n.to_string()).unwrap_or_default();
+    let scores = |s: Option<RegressionScores>| match s {
+        Some(s) => format!("{},{},{},{}", s.mse, s.mae, s.rmse, s.r2),
+        None => ",,,".to_string()
+    };
+
+    format!(
+        "{},{},{},{},{},{},{},{},{},{}",
+        metrics.epoch + 1,
+        metrics.loss,
+        metrics.accuracy,
+        optional(metrics.valid_loss),
+        optional(metrics.valid_accuracy),
+        metrics.grad_norm,
+        metrics.learn_rate,
+        metrics.time,
+        scores(metrics.regression),
+        scores(metrics.valid_regression)
+    )
+}
@@ -1,9 +1,10 @@
-use std::{ops::{Index, IndexMut, Deref}, process::Output};
+use std::ops::{Index, IndexMut};
 
 use rand::{distributions::Standard, prelude::Distribution, Rng};
 use serde_derive::{Serialize, Deserialize};
 
 use crate::num::{Num, Int};
+use crate::error::{NetError, Result};
 
 pub trait LinAlg<N: Num=f32> 
 where
@@ -106,22 +107,34 @@ where
 
     /// Sets self to element-wise multipication matrix
     fn dot_eq<M: LinAlgGen<N>>(&mut self, rhs: &M) -> &mut Self {
-        if self.shape() != rhs.shape() {
-            panic!("unmatched 'dot-eq' dimensions {:?} | {:?}", self.shape(), rhs.shape())
+        match self.try_dot_eq(rhs) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
         }
+    }
+
+    /// Sets self to element-wise multipication matrix, failing on unmatched dimensions
+    fn try_dot_eq<M: LinAlgGen<N>>(&mut self, rhs: &M) -> Result<&mut Self> {
+        self.check_shape("dot-eq", rhs)?;
 
         for (n, other) in self.buf_mut().iter_mut().zip(rhs.buf().iter()) {
             *n *= *other;
         }
         
-        self
+        Ok(self)
     }
 
     /// Returns a new element-wise multipication matrix
     fn dot<M: LinAlgGen<N>>(&self, rhs: &M) -> Self {
-        if self.shape() != rhs.shape() {
-            panic!("unmatched 'dot' dimensions {:?} | {:?}", self.shape(), rhs.shape())
+        match self.try_dot(rhs) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
         }
+    }
+
+    /// Returns a new element-wise multipication matrix, failing on unmatched dimensions
+    fn try_dot<M: LinAlgGen<N>>(&self, rhs: &M) -> Result<Self> {
+        self.check_shape("dot", rhs)?;
 
         let buf = self
             .buf()
@@ -130,7 +143,7 @@ where
             .map(|(n, other)| *n * *other)
             .collect();
 
-        Self::from_buf(Self::to_dim(self.shape()), buf)
+        Ok(Self::from_buf(Self::to_dim(self.shape()), buf))
     }
 
     /// Returns matrix scaled by scalar
@@ -193,22 +206,34 @@ where
 
     /// Sets self to the sum of the two matrices
     fn add_eq<M: LinAlgGen<N>>(&mut self, rhs: &M) -> &mut Self {
-        if self.shape() != rhs.shape() {
-            panic!("unmatched 'add-eq' dimensions {:?} | {:?}", self.shape(), rhs.shape())
+        match self.try_add_eq(rhs) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
         }
+    }
+
+    /// Sets self to the sum of the two matrices, failing on unmatched dimensions
+    fn try_add_eq<M: LinAlgGen<N>>(&mut self, rhs: &M) -> Result<&mut Self> {
+        self.check_shape("add-eq", rhs)?;
 
         for (n, other) in self.buf_mut().iter_mut().zip(rhs.buf().iter()) {
             *n += *other;
         }
         
-        self
+        Ok(self)
     }
 
     /// Returns new sum matrix
     fn add<M: LinAlgGen<N>>(&self, rhs: &M) -> Self {
-        if self.shape() != rhs.shape() {
-            panic!("unmatched 'add' dimensions {:?} | {:?}", self.shape(), rhs.shape())
+        match self.try_add(rhs) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
         }
+    }
+
+    /// Returns new sum matrix, failing on unmatched dimensions
+    fn try_add<M: LinAlgGen<N>>(&self, rhs: &M) -> Result<Self> {
+        self.check_shape("add", rhs)?;
 
         let buf = self
             .buf()
@@ -217,27 +242,39 @@ where
             .map(|(n, other)| *n + *other)
             .collect();
         
-        Self::from_buf(Self::to_dim(self.shape()), buf)
+        Ok(Self::from_buf(Self::to_dim(self.shape()), buf))
     }
 
     /// Sets self to the difference of the two matrices
     fn sub_eq<M: LinAlgGen<N>>(&mut self, rhs: &M) -> &mut Self {
-        if self.shape() != rhs.shape() {
-            panic!("unmatched 'sub-eq' dimensions {:?} | {:?}", self.shape(), rhs.shape())
+        match self.try_sub_eq(rhs) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
         }
+    }
+
+    /// Sets self to the difference of the two matrices, failing on unmatched dimensions
+    fn try_sub_eq<M: LinAlgGen<N>>(&mut self, rhs: &M) -> Result<&mut Self> {
+        self.check_shape("sub-eq", rhs)?;
 
         for (n, other) in self.buf_mut().iter_mut().zip(rhs.buf().iter()) {
             *n -= *other;
         }
 
-        self
+        Ok(self)
     }
 
     /// Returns new difference matrix
     fn sub<M: LinAlgGen<N>>(&self, rhs: &M) -> Self {
-        if self.shape() != rhs.shape() {
-            panic!("unmatched 'sub' dimensions {:?} | {:?}", self.shape(), rhs.shape())
+        match self.try_sub(rhs) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
         }
+    }
+
+    /// Returns new difference matrix, failing on unmatched dimensions
+    fn try_sub<M: LinAlgGen<N>>(&self, rhs: &M) -> Result<Self> {
+        self.check_shape("sub", rhs)?;
 
         let buf = self
             .buf()
@@ -246,7 +283,16 @@ where
             .map(|(n, other)| *n - *other)
             .collect();
         
-        Self::from_buf(Self::to_dim(self.shape()), buf)
+        Ok(Self::from_buf(Self::to_dim(self.shape()), buf))
+    }
+
+    /// Fails if the two matrices are of unmatched dimensions
+    fn check_shape<M: LinAlgGen<N>>(&self, op: &'static str, rhs: &M) -> Result<()> {
+        if self.shape() != rhs.shape() {
+            return Err(NetError::Dimension { op, lhs: self.shape(), rhs: rhs.shape() })
+        }
+
+        Ok(())
     }
 
     /// Returns vector as matrix with diagonal elements
@@ -356,7 +402,85 @@ where
     }
 }
 
-impl<N: Num + Int, T: LinAlgGen<N>> LinAlgMul<N> for T {}
+impl<N: Num + Int, T: LinAlgGen<N>> LinAlgMul<N> for T {
+
+    fn mul_to<'a, M, K>(&self, rhs: &M, buf: &'a mut K) -> &'a mut K
+    where
+        M: LinAlg<N>,
+        K: LinAlg<N>
+    {
+        if self.col() != rhs.row() {
+            panic!("cannot multiply {:?} by {:?}", self.shape(), rhs.shape())
+        }
+
+        buf.fill_zero();
+
+        for r in 0..self.row() {
+            for k in 0..self.col() {
+                let lhs = self.buf()[self.to_index((r, k))];
+
+                for c in 0..rhs.col() {
+                    let i = buf.to_index((r, c));
+                    buf.buf_mut()[i] += lhs * rhs.buf()[rhs.to_index((k, c))];
+                }
+            }
+        }
+
+        buf
+    }
+
+    fn mul_t1_to<'a, M, K>(&self, rhs: &M, buf: &'a mut K) -> &'a mut K
+    where
+        M: LinAlg<N>,
+        K: LinAlg<N>
+    {
+        if self.row() != rhs.row() {
+            panic!("cannot multiply T{:?} by {:?}", self.shape(), rhs.shape())
+        }
+
+        buf.fill_zero();
+
+        for k in 0..self.row() {
+            for r in 0..self.col() {
+                let lhs = self.buf()[self.to_index((k, r))];
+
+                for c in 0..rhs.col() {
+                    let i = buf.to_index((r, c));
+                    buf.buf_mut()[i] += lhs * rhs.buf()[rhs.to_index((k, c))];
+                }
+            }
+        }
+
+        buf
+    }
+
+    fn mul_t2_to<'a, M, K>(&self, rhs: &M, buf: &'a mut K) -> &'a mut K
+    where
+        M: LinAlg<N>,
+        K: LinAlg<N>
+    {
+        if self.col() != rhs.col() {
+            panic!("cannot multiply {:?} by T{:?}", self.shape(), rhs.shape())
+        }
+
+        buf.fill_zero();
+
+        for r in 0..self.row() {
+            for c in 0..rhs.row() {
+                let mut sum = N::zero();
+
+                for k in 0..self.col() {
+                    sum += self.buf()[self.to_index((r, k))] * rhs.buf()[rhs.to_index((c, k))];
+                }
+
+                let i = buf.to_index((r, c));
+                buf.buf_mut()[i] = sum;
+            }
+        }
+
+        buf
+    }
+}
 
 impl<T: LinAlgGen<f32>> LinAlgMul<f32> for T {
     
@@ -563,12 +687,33 @@ impl<T: LinAlgGen<f64>> LinAlgMul<f64> for T {
 }
 
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawMatrix<N>")]
 pub struct Matrix<N: Num=f32> {
     buf: Vec<N>,
     row: usize,
     col: usize
 }
 
+/// Serialized matrix whose buffer is yet to be checked against its shape
+#[derive(Deserialize)]
+struct RawMatrix<N> {
+    buf: Vec<N>,
+    row: usize,
+    col: usize
+}
+
+impl<N: Num> TryFrom<RawMatrix<N>> for Matrix<N> {
+    type Error = NetError;
+
+    fn try_from(RawMatrix { buf, row, col }: RawMatrix<N>) -> Result<Self> {
+        if row.checked_mul(col) != Some(buf.len()) {
+            return Err(NetError::Buffer { shape: (row, col), len: buf.len() })
+        }
+
+        Ok(Self { buf, row, col })
+    }
+}
+
 impl<N: Num> LinAlg<N> for Matrix<N> where Matrix<N>: LinAlgMul<N> 
 {}
 
@@ -639,11 +784,31 @@ impl<N: Num> Matrix<N> {
 }
 
 #[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RawVector<N>")]
 pub struct Vector<N: Num=f32> {
     buf: Vec<N>,
     row: usize
 }
 
+/// Serialized vector whose buffer is yet to be checked against its shape
+#[derive(Deserialize)]
+struct RawVector<N> {
+    buf: Vec<N>,
+    row: usize
+}
+
+impl<N: Num> TryFrom<RawVector<N>> for Vector<N> {
+    type Error = NetError;
+
+    fn try_from(RawVector { buf, row }: RawVector<N>) -> Result<Self> {
+        if row != buf.len() {
+            return Err(NetError::Buffer { shape: (row, 1), len: buf.len() })
+        }
+
+        Ok(Self { buf, row })
+    }
+}
+
 impl<N: Num> Index<usize> for Vector<N> {
     type Output=N;
 
@@ -724,4 +889,21 @@ impl<N: Num> Vector<N> {
         
         max
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn integer_products_match_their_definition() {
+        let lhs = Matrix::from_arr([[1_i32, -2, 3], [4, 5, -6]]);
+        let rhs = Matrix::from_arr([[7_i32, 8], [-9, 10], [11, -12]]);
+
+        let product: Matrix<i32> = lhs.mul(&rhs);
+        assert_eq!(product.buf(), &vec![58, -48, -83, 154]);
+
+        let product_t1: Matrix<i32> = lhs.transpose().mul_t1(&rhs);
+        let product_t2: Matrix<i32> = lhs.mul_t2(&rhs.transpose());
+        assert!(product_t1 == product && product_t2 == product);
+    }
+}
@@ -1,12 +1,7 @@
-use std::path::Path;
-
 use draw::run_sketch;
-use linalg::{Matrix, Vector};
 use mnist::Reader;
 use net::Net;
 
-use crate::{mnist::{DataType::*, TEST_IMAGES, TRAIN_IMAGES}, linalg::{LinAlgGen, LinAlgMul}};
-
 
 pub mod array;
 pub mod net;
@@ -15,6 +10,28 @@ pub mod cost;
 pub mod step;
 pub mod num;
 pub mod linalg;
+pub mod transformer;
+pub mod callback;
+pub mod history;
+pub mod gradient;
+pub mod error;
+pub mod format;
+pub mod model;
+pub mod progress;
+pub mod predict;
+pub mod classify;
+pub mod evaluation;
+pub mod regression;
+pub mod multilabel;
+pub mod quantize;
+pub mod prune;
+pub mod distill;
+pub mod ensemble;
+pub mod transfer;
+pub mod surgery;
+pub mod search;
+pub mod crossval;
+pub mod summary;
 mod draw;
 
 fn main() {   
@@ -30,9 +47,9 @@ fn main() {
     // net.train();
     // net.save();
 
-    let mut net = Net::<4>::from_file("src/models/digit_hp.json");
+    let net = Net::<4>::from_file("src/models/digit_hp.json");
 
-    let acc = net.accuracy(&mnist.test_images(), &mnist.test_labels());
+    let acc = net.accuracy(mnist.test_images(), mnist.test_labels());
     println!("acc: {}", acc);
     // net.data
     //     .with_epoch_stats(false)
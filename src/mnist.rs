@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::Path;
 use crate::linalg::Vector;
 use crate::linalg::LinAlgGen;
 
@@ -13,7 +13,6 @@ use crate::linalg::LinAlgGen;
 /// 0009       ???              label
 /// ...
 /// xxxx       ???              label
-
 const LABEL_MAGIC_NUMBER: u32 = 2049;
 const TRAIN_LABELS_PATH: &str = "src/res/train-labels";
 const TEST_LABELS_PATH: &str =  "src/res/test_labels";
@@ -35,7 +34,6 @@ const LABEL_DATA_OFFSET: usize = 8;
 /// 
 /// TRAIN IMAGES: 60,000
 /// TEST IMAGES:  10,000
-
 const IMAGE_MAGIC_NUMBER: u32 = 2051;
 const TRAIN_IMAGES_PATH: &str = "src/res/train-images";
 const TEST_IMAGES_PATH: &str =  "src/res/test-images";
@@ -54,6 +52,12 @@ pub struct Reader {
     test_labels:  Vec<Vector>
 }
 
+impl Default for Reader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 pub enum DataType {
     Train,
     Test
@@ -79,7 +83,7 @@ impl Reader {
         println!("test labels: {}", self.test_images.len());
     }
 
-    fn read_labels(data_type: DataType, work_dir: &PathBuf) -> Vec<Vector> {
+    fn read_labels(data_type: DataType, work_dir: &Path) -> Vec<Vector> {
         let path = match data_type {
             DataType::Train => TRAIN_LABELS_PATH,
             DataType::Test =>  TEST_LABELS_PATH
@@ -103,7 +107,7 @@ impl Reader {
             .collect()
     }
 
-    fn read_images(data_type: DataType, work_dir: &PathBuf) -> Vec<Vector> {
+    fn read_images(data_type: DataType, work_dir: &Path) -> Vec<Vector> {
         let path = match data_type {
             DataType::Train => TRAIN_IMAGES_PATH,
             DataType::Test =>  TEST_IMAGES_PATH
@@ -152,12 +156,10 @@ impl Reader {
     }
 
     pub fn image_string(&self, data_type: DataType, index: usize) -> String {
-        let images;
-        
-        match data_type {
-            DataType::Train => images = &self.train_images,
-            DataType::Test  => images = &self.test_images
-        }
+        let images = match data_type {
+            DataType::Train => &self.train_images,
+            DataType::Test  => &self.test_images
+        };
 
         images[index]
             .buf()
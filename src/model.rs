@@ -0,0 +1,169 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use serde_derive::{Serialize, Deserialize};
+
+use crate::array::Array;
+use crate::error::{NetError, Result};
+use crate::format;
+use crate::linalg::*;
+use crate::net::Net;
+use crate::step::{Activation, Output};
+
+/// Inference-only network holding just the architecture, weights,
+/// biases and activation of a trained `Net`, without training buffers
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Model {
+    // layer sizes
+    form: Vec<usize>,
+
+    weights: Array<Matrix>,
+
+    biases: Array<Vector>,
+
+    // step function
+    act: Activation,
+
+    // output layer function
+    #[serde(default)]
+    out: Output
+}
+
+/// Binary format meta data of a model
+#[derive(Serialize, Deserialize)]
+struct Meta {
+    act: Activation,
+
+    #[serde(default)]
+    out: Output
+}
+
+impl<const L: usize> From<&Net<L>> for Model {
+    fn from(net: &Net<L>) -> Self {
+        Self {
+            form: net.data.form.clone(),
+            weights: net.weights.clone(),
+            biases: net.biases.clone(),
+            act: net.data.act,
+            out: net.data.out
+        }
+    }
+}
+
+impl Model {
+    pub fn form(&self) -> &[usize] {
+        &self.form
+    }
+
+    pub fn act(&self) -> Activation {
+        self.act
+    }
+
+    pub fn out(&self) -> Output {
+        self.out
+    }
+
+    pub fn forward_prop(&self, input: &Vector) -> Vector {
+        match self.try_forward_prop(input) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    pub fn try_forward_prop(&self, input: &Vector) -> Result<Vector> {
+        if input.shape() != (self.form[0], 1) {
+            return Err(NetError::Shape { expected: (self.form[0], 1), found: input.shape() })
+        }
+
+        let mut act = input.clone();
+
+        for l in 0..self.weights.len() {
+            let mut sum = Vector::from_zeros(self.form[l+1]);
+
+            self.weights[l].mul_to(&act, &mut sum);
+            sum.add_eq(&self.biases[l]);
+
+            if l == self.weights.len()-1 {
+                self.out.value_eq(self.act, &mut sum);
+            }
+            else {
+                sum.map_eq(|n| self.act.value(n));
+            }
+
+            act = sum;
+        }
+
+        Ok(act)
+    }
+
+    /// Atomically writes the versioned model to path
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_versioned(self, path)
+    }
+
+    /// Writes the versioned model to writer
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<()> {
+        format::write_versioned(self, writer)
+    }
+
+    /// Atomically writes the model to path in the compact binary format
+    pub fn save_binary_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::write_atomic(path, &self.to_binary()?)
+    }
+
+    /// Writes the model to writer in the compact binary format
+    pub fn write_binary_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(&self.to_binary()?)?;
+        Ok(())
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Self {
+        Self::try_from_file(path)
+            .unwrap_or_else(|err| panic!("couldn't load model: {}", err))
+    }
+
+    /// Reads a JSON or binary model from path
+    pub fn try_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_from(format::open(path)?)
+    }
+
+    /// Reads a JSON or binary model from reader
+    pub fn read_from<R: Read>(reader: R) -> Result<Self> {
+        let bytes = format::read_all(reader)?;
+
+        let model = if format::is_binary(&bytes) {
+            Self::from_binary(&bytes)?
+        }
+        else {
+            format::from_versioned(&bytes)?
+        };
+
+        model.check_form()?;
+
+        Ok(model)
+    }
+
+    fn to_binary(&self) -> Result<Vec<u8>> {
+        let meta = serde_json::to_vec(&Meta { act: self.act, out: self.out })?;
+        Ok(format::to_binary(&self.form, &meta, &self.weights.buf, &self.biases.buf, None))
+    }
+
+    fn from_binary(bytes: &[u8]) -> Result<Self> {
+        let binary = format::from_binary(bytes)?;
+
+        let meta: Meta = serde_json::from_slice(&binary.meta)?;
+
+        Ok(Self {
+            act: meta.act,
+            out: meta.out,
+            form: binary.form,
+            weights: Array::from_buf(binary.weights),
+            biases: Array::from_buf(binary.biases)
+        })
+    }
+
+    /// Fails if the layer buffers don't match the model form
+    pub(crate) fn check_form(&self) -> Result<()> {
+        format::check_form(&self.form, self.weights.shapes(), self.biases.shapes())
+    }
+}
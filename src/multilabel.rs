@@ -0,0 +1,218 @@
+use std::fmt;
+use std::path::Path;
+
+use serde_derive::{Serialize, Deserialize};
+
+use crate::error::{NetError, Result};
+use crate::format;
+use crate::evaluation::Scores;
+use crate::linalg::*;
+
+/// Default decision threshold of a label
+pub const THRESHOLD: f32 = 0.5;
+
+/// Scores of a single label with its number of positive samples
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct LabelScores {
+    pub label: usize,
+    pub scores: Scores,
+    pub support: usize
+}
+
+/// Multi-label report of a network over a data set
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MultiLabel {
+    // evaluated samples
+    pub samples: usize,
+
+    // fraction of wrongly decided labels
+    pub hamming_loss: f32,
+
+    // fraction of samples with every label decided right
+    pub subset_accuracy: f32,
+
+    // per-label scores
+    pub labels: Vec<LabelScores>,
+
+    // unweighted mean of the per-label scores
+    pub macro_avg: Scores,
+
+    // scores of the summed per-label counts
+    pub micro_avg: Scores
+}
+
+/// Returns the labels whose output reaches its threshold, a single
+/// threshold applies to every label and none falls back to `THRESHOLD`
+pub fn decide(out: &Vector, thresholds: &[f32]) -> Vec<bool> {
+    out.buf()
+        .iter()
+        .enumerate()
+        .map(|(label, prob)| *prob >= threshold(thresholds, label))
+        .collect()
+}
+
+/// Returns the threshold of each label maximizing its F1 score
+/// over the given outputs and multi-hot targets
+pub fn tune_thresholds(outs: &[Vector], targets: &[Vector]) -> Vec<f32> {
+    let labels = targets.first().map_or(0, |t| t.row());
+
+    (0..labels)
+        .map(|label| {
+            let mut candidates: Vec<f32> = outs.iter().map(|out| out[label]).collect();
+            candidates.sort_by(f32::total_cmp);
+            candidates.dedup();
+
+            let f1 = |threshold: f32| {
+                let (mut tp, mut fp, mut fn_) = (0, 0, 0);
+
+                for (out, target) in outs.iter().zip(targets) {
+                    match (out[label] >= threshold, target[label] >= THRESHOLD) {
+                        (true, true) => tp += 1,
+                        (true, false) => fp += 1,
+                        (false, true) => fn_ += 1,
+                        (false, false) => ()
+                    }
+                }
+
+                Scores::from_counts(tp, fp, fn_).f1
+            };
+
+            // the first candidate of the highest score wins ties
+            candidates
+                .into_iter()
+                .map(|threshold| (threshold, f1(threshold)))
+                .fold(None, |best: Option<(f32, f32)>, (threshold, score)| match best {
+                    Some(best) if best.1 >= score => Some(best),
+                    _ => Some((threshold, score))
+                })
+                .map_or(THRESHOLD, |(threshold, _)| threshold)
+        })
+        .collect()
+}
+
+impl MultiLabel {
+    /// Builds the report from label probabilities and multi-hot targets
+    pub fn from_outputs(outs: &[Vector], targets: &[Vector], thresholds: &[f32]) -> Self {
+        Self::try_from_outputs(outs, targets, thresholds)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_from_outputs(outs: &[Vector], targets: &[Vector], thresholds: &[f32]) -> Result<Self> {
+        if outs.len() != targets.len() {
+            return Err(NetError::Length { inputs: outs.len(), targets: targets.len() })
+        }
+
+        let labels = outs.first().map_or(0, |out| out.row());
+
+        if thresholds.len() > 1 && thresholds.len() != labels {
+            return Err(NetError::Shape { expected: (labels, 1), found: (thresholds.len(), 1) })
+        }
+
+        // true positives, false positives and false negatives per label
+        let mut counts = vec![(0, 0, 0); labels];
+        let mut wrong = 0;
+        let mut exact = 0;
+
+        for (out, target) in outs.iter().zip(targets) {
+            if out.shape() != (labels, 1) {
+                return Err(NetError::Shape { expected: (labels, 1), found: out.shape() })
+            }
+            if target.shape() != (labels, 1) {
+                return Err(NetError::Shape { expected: (labels, 1), found: target.shape() })
+            }
+
+            let decided = decide(out, thresholds);
+            let mut sample_wrong = 0;
+
+            for (label, predicted) in decided.into_iter().enumerate() {
+                let count = &mut counts[label];
+
+                match (predicted, target[label] >= THRESHOLD) {
+                    (true, true) => count.0 += 1,
+                    (true, false) => count.1 += 1,
+                    (false, true) => count.2 += 1,
+                    (false, false) => ()
+                }
+
+                if predicted != (target[label] >= THRESHOLD) {
+                    sample_wrong += 1;
+                }
+            }
+
+            wrong += sample_wrong;
+            if sample_wrong == 0 {
+                exact += 1;
+            }
+        }
+
+        let label_scores: Vec<LabelScores> = counts
+            .iter()
+            .enumerate()
+            .map(|(label, (tp, fp, fn_))| LabelScores {
+                label,
+                scores: Scores::from_counts(*tp, *fp, *fn_),
+                support: tp + fn_
+            })
+            .collect();
+
+        let mean = |score: fn(&Scores) -> f32| {
+            label_scores.iter().map(|l| score(&l.scores)).sum::<f32>() / labels.max(1) as f32
+        };
+
+        let macro_avg = Scores {
+            precision: mean(|s| s.precision),
+            recall: mean(|s| s.recall),
+            f1: mean(|s| s.f1)
+        };
+
+        let total = counts
+            .iter()
+            .fold((0, 0, 0), |acc, c| (acc.0 + c.0, acc.1 + c.1, acc.2 + c.2));
+
+        let samples = outs.len().max(1) as f32;
+
+        Ok(Self {
+            samples: outs.len(),
+            hamming_loss: wrong as f32 / (samples * labels.max(1) as f32),
+            subset_accuracy: exact as f32 / samples,
+            labels: label_scores,
+            macro_avg,
+            micro_avg: Scores::from_counts(total.0, total.1, total.2)
+        })
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        format::to_json(self)
+    }
+
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_json(self, path)
+    }
+}
+
+impl fmt::Display for MultiLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>9} {:>9} {:>9} {:>9} {:>9}", "label", "precision", "recall", "f1", "support")?;
+
+        for label in self.labels.iter() {
+            let s = label.scores;
+            writeln!(f, "{:>9} {:>9.3} {:>9.3} {:>9.3} {:>9}", label.label, s.precision, s.recall, s.f1, label.support)?;
+        }
+
+        writeln!(f)?;
+        for (name, s) in [("macro", self.macro_avg), ("micro", self.micro_avg)] {
+            writeln!(f, "{:>9} {:>9.3} {:>9.3} {:>9.3}", name, s.precision, s.recall, s.f1)?;
+        }
+
+        writeln!(f, "\nhamming loss: {:.4}", self.hamming_loss)?;
+        writeln!(f, "subset accuracy: {:.4}", self.subset_accuracy)
+    }
+}
+
+fn threshold(thresholds: &[f32], label: usize) -> f32 {
+    match thresholds {
+        [] => THRESHOLD,
+        [threshold] => *threshold,
+        thresholds => thresholds[label]
+    }
+}
@@ -1,13 +1,25 @@
 // TODO: make LinAlg contain col --> index by stride so transpose doesn't have to reallocate buffer
 
-use std::{ops::Range, fs::File};
+use std::{ops::Range, time::Instant};
+use std::io::{Read, Write};
+use std::path::Path;
 
 use crate::array::Array;
 use crate::array::IndexType::Back;
 
-use super::step::Activation;
+use super::step::{Activation, Output};
 use super::cost::Cost;
 use super::linalg::*;
+use super::callback::*;
+use super::history::History;
+use super::gradient::Clip;
+use super::error::{NetError, Result};
+use super::format;
+use super::progress::{Best, Progress};
+use super::predict::Scratch;
+use super::regression::RegressionScores;
+use super::classify::Classifier;
+use super::distill::SoftTargets;
 
 use serde_derive::{Serialize, Deserialize};
 
@@ -20,20 +32,38 @@ const ACTIVATION: Activation = Activation::Tanh;
 /// Default network cost function
 const COST: Cost = Cost::Quad;
 
+/// Kind of problem a network is trained on, selecting the metrics
+/// reported alongside the loss during training
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Task {
+    // one-hot targets scored by accuracy
+    #[default]
+    Classification,
+
+    // continuous targets scored by regression metrics
+    Regression,
+
+    // multi-hot targets scored by subset accuracy
+    MultiLabel
+}
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct HyperData<const L: usize> {    
     // layer sizes
-    form: Vec<usize>,
+    pub(crate) form: Vec<usize>,
 
     // size of batch sampling
-    batch_size: usize,
+    pub(crate) batch_size: usize,
 
     // learning coefficient
-    learn_rate: f32,
+    pub(crate) learn_rate: f32,
 
     // step function
-    act: Activation,
+    pub(crate) act: Activation,
+
+    // output layer function
+    #[serde(default)]
+    pub(crate) out: Output,
 
     // cost function
     cost: Cost,
@@ -42,10 +72,34 @@ pub struct HyperData<const L: usize> {
     dir: String,
 
     // controls printing of epochs
-    stat_epoch: bool,
+    pub(crate) stat_epoch: bool,
 
     // controls printing of error
-    stat_error: bool
+    pub(crate) stat_error: bool,
+
+    // epochs without validation improvement before stopping
+    #[serde(default)]
+    pub(crate) patience: Option<usize>,
+
+    // controls saving of the best validated model to dir
+    #[serde(default)]
+    pub(crate) save_best: bool,
+
+    // gradient clipping
+    #[serde(default)]
+    clip: Option<Clip>,
+
+    // controls shuffling of the training data every epoch
+    #[serde(default)]
+    shuffle: bool,
+
+    // kind of problem, selecting the reported training metrics
+    #[serde(default)]
+    pub(crate) task: Task,
+
+    // weight layers left unchanged by training
+    #[serde(default)]
+    pub(crate) frozen: Vec<usize>
 }
 
 impl<const L: usize> From<[usize; L]> for HyperData<L> {
@@ -55,10 +109,17 @@ impl<const L: usize> From<[usize; L]> for HyperData<L> {
             batch_size: BATCH_SIZE, 
             learn_rate: LEARN_RATE, 
             act: ACTIVATION, 
+            out: Output::Act,
             cost: COST,
             dir: String::new(),
             stat_error: false,
-            stat_epoch: false
+            stat_epoch: false,
+            patience: None,
+            save_best: false,
+            clip: None,
+            shuffle: false,
+            task: Task::Classification,
+            frozen: Vec::new()
         }    
     }
 }
@@ -79,6 +140,22 @@ impl<const L: usize> HyperData<L> {
         self
     }
 
+    pub fn with_task(&mut self, task: Task) -> &mut Self {
+        self.task = task;
+        self
+    }
+
+    /// Freezes the given weight layers, indexed from the input
+    pub fn with_frozen(&mut self, layers: &[usize]) -> &mut Self {
+        self.frozen = layers.to_vec();
+        self
+    }
+
+    pub fn with_output(&mut self, out: Output) -> &mut Self {
+        self.out = out;
+        self
+    }
+
     pub fn with_cost(&mut self, cost: Cost) -> &mut Self {
         self.cost = cost;
         self
@@ -99,10 +176,108 @@ impl<const L: usize> HyperData<L> {
         self
     }
 
+    /// Stops after epochs without validation improvement, training fails without a validation set
+    pub fn with_patience(&mut self, epochs: usize) -> &mut Self {
+        self.patience = Some(epochs);
+        self
+    }
+
+    /// Saves the best validated model to dir, training fails without a validation set
+    pub fn with_save_best(&mut self, state: bool) -> &mut Self {
+        self.save_best = state;
+        self
+    }
+
+    /// Clips every batch gradient, training fails on a bound that isn't positive
+    pub fn with_clip(&mut self, clip: Clip) -> &mut Self {
+        self.clip = Some(clip);
+        self
+    }
+
+    pub fn with_shuffle(&mut self, state: bool) -> &mut Self {
+        self.shuffle = state;
+        self
+    }
+
     pub fn build(&self) -> Net<L> {
         Net::from_parts(self.clone())
     }
 
+    /// Returns the hyper parameters for a net of another form,
+    /// failing if its length doesn't match the const layer count
+    pub(crate) fn reform<const M: usize>(&self, form: Vec<usize>, frozen: Vec<usize>) -> Result<HyperData<M>> {
+        if form.len() != M {
+            return Err(NetError::LayerCount { expected: M, found: form.len() })
+        }
+
+        Ok(HyperData {
+            form,
+            batch_size: self.batch_size,
+            learn_rate: self.learn_rate,
+            act: self.act,
+            out: self.out,
+            cost: self.cost,
+            dir: self.dir.clone(),
+            stat_epoch: self.stat_epoch,
+            stat_error: self.stat_error,
+            patience: self.patience,
+            save_best: self.save_best,
+            clip: self.clip,
+            shuffle: self.shuffle,
+            task: self.task,
+            frozen
+        })
+    }
+
+    /// Fails on invalid training or validation data, or on hyper parameters
+    /// that a net couldn't be built or trained with, without building one
+    pub(crate) fn check_training(&self, inputs: &[Vector], targets: &[Vector], valid: Option<(&[Vector], &[Vector])>) -> Result<()> {
+        if self.form.len() <= 2 {
+            return Err(NetError::TooFewLayers(self.form.len()))
+        }
+
+        self.check_data(inputs, targets)?;
+
+        match valid {
+            Some((valid_inputs, valid_targets)) => self.check_data(valid_inputs, valid_targets)?,
+            None if self.patience.is_some() => return Err(NetError::Config("patience requires a validation set")),
+            None if self.save_best => return Err(NetError::Config("save_best requires a validation set")),
+            None => ()
+        }
+
+        if self.save_best && self.dir.is_empty() {
+            return Err(NetError::Config("save_best requires a save directory"))
+        }
+
+        if self.clip.is_some_and(|clip| !clip.is_valid()) {
+            return Err(NetError::Config("clip bound must be positive"))
+        }
+
+        Ok(())
+    }
+
+    /// Fails on unequal amounts of data or wrongly shaped inputs and targets
+    pub(crate) fn check_data(&self, inputs: &[Vector], targets: &[Vector]) -> Result<()> {
+        if inputs.len() != targets.len() {
+            return Err(NetError::Length { inputs: inputs.len(), targets: targets.len() })
+        }
+
+        let input_shape = (self.form[0], 1);
+        let target_shape = (self.form[L-1], 1);
+
+        for (input, target) in inputs.iter().zip(targets) {
+            if input.shape() != input_shape {
+                return Err(NetError::Shape { expected: input_shape, found: input.shape() })
+            }
+
+            if target.shape() != target_shape {
+                return Err(NetError::Shape { expected: target_shape, found: target.shape() })
+            }
+        }
+
+        Ok(())
+    }
+
     fn zero_array<M, R, F>(&self, range: R, func: F) -> Array<M>
     where
         M: LinAlg,
@@ -129,29 +304,48 @@ impl<const L: usize> HyperData<L> {
         Array::from_buf(buf)
     }
     
-    fn act(&self, n: f32) -> f32 {
-        self.act.value(n)
+    /// Applies the step function to the sums of layer l,
+    /// or the output function to those of the last layer
+    pub(crate) fn activate_eq(&self, l: usize, sums: &mut Vector) {
+        if l == self.form.len()-2 {
+            self.out.value_eq(self.act, sums);
+        }
+        else {
+            sums.map_eq(|n| self.act.value(n));
+        }
     }
 
     fn d_act(&self, n: f32) -> f32 {
         self.act.deriv(n)
     }
 
-    fn cost(&self, diff: f32) -> f32 {
-        self.cost.value(diff)
+    fn cost(&self, out: &Vector, target: &Vector) -> f32 {
+        out.buf()
+            .iter()
+            .zip(target.buf())
+            .map(|(a, y)| self.cost.value(*a, *y))
+            .sum()
     }
 
-    fn d_cost(&self, diff: f32) -> f32 {
-        self.cost.deriv(diff)
+    fn d_cost(&self, out: &Vector, target: &Vector) -> Vector {
+        Vector::from_buf(out.row(), out.buf()
+            .iter()
+            .zip(target.buf())
+            .map(|(a, y)| self.cost.deriv(*a, *y))
+            .collect())
     }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Net<const L: usize> {
     
-    weights: Array<Matrix>,
+    pub(crate) weights: Array<Matrix>,
     
-    biases: Array<Vector>,
+    pub(crate) biases: Array<Vector>,
+
+    // pruning masks, zero where weights are pruned
+    #[serde(default)]
+    pub(crate) masks: Option<Array<Matrix>>,
     
     /// Training Data ///
 
@@ -159,27 +353,38 @@ pub struct Net<const L: usize> {
     acts: Array<Vector>,
     
     // layer summations buffer
-    sums: Array<Vector>,
+    pub(crate) sums: Array<Vector>,
     
     // layer errors buffer
-    err: Array<Vector>,
+    pub(crate) err: Array<Vector>,
     
     // layer errors accumulator
-    acc_err: Array<Vector>,
+    pub(crate) acc_err: Array<Vector>,
     
     // weight errors buffer
-    w_err: Array<Matrix>,
+    pub(crate) w_err: Array<Matrix>,
     
     // weight error accumulator
-    acc_w_err: Array<Matrix>,
+    pub(crate) acc_w_err: Array<Matrix>,
 
     // current number of error samples
     acc_samples: usize,
 
+    // in-flight training run, only saved in checkpoints
+    #[serde(skip)]
+    progress: Progress,
+
     // hyper parameters
     pub data: HyperData<L>
 }
 
+/// Checkpoint file contents, a model with its in-flight training run
+#[derive(Serialize, Deserialize)]
+struct Checkpointed<N, P> {
+    net: N,
+    progress: P
+}
+
 impl<const L: usize> From<[usize; L]> for Net<L> {  
     fn from(form: [usize; L]) -> Self {
         Self::from_parts(HyperData::from(form))
@@ -188,11 +393,15 @@ impl<const L: usize> From<[usize; L]> for Net<L> {
 
 impl<const L: usize> Net<L> {
     pub fn from_parts(data: HyperData<L>) -> Self {
+        Self::try_from_parts(data).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_from_parts(data: HyperData<L>) -> Result<Self> {
         if data.form.len() <= 2 {
-            panic!()
+            return Err(NetError::TooFewLayers(data.form.len()))
         }
 
-        Self { 
+        Ok(Self { 
             acts:      data.zero_array(|l| 0..l,   |i, f| f[i]),
             sums:      data.zero_array(|l| 1..l,   |i, f| f[i]),
             err:       data.zero_array(|l| 1..l,   |i, f| f[i]),
@@ -202,8 +411,10 @@ impl<const L: usize> Net<L> {
             w_err:     data.zero_array(|l| 0..l-1, |i, f| (f[i+1], f[i])),
             acc_w_err: data.zero_array(|l| 0..l-1, |i, f| (f[i+1], f[i])),
             acc_samples: 0,
+            masks: None,
+            progress: Progress::default(),
             data,
-        }
+        })
     }
 
     pub fn new(form: [usize; L]) -> HyperData<L> {
@@ -211,36 +422,142 @@ impl<const L: usize> Net<L> {
     }
 
     pub fn save(&self) {
-        let net = serde_json::to_string(&self)
-            .expect("could not convert model to string!");
+        self.try_save()
+            .unwrap_or_else(|err| panic!("couldn't save model: {}", err))
+    }
 
-        let work_dir = std::env::current_dir()
-            .expect("invalid working directory!");
+    pub fn try_save(&self) -> Result<()> {
+        self.save_to(&self.data.dir)
+    }
 
-        // create reference to absolute path
-        let abs_path = work_dir.join(&self.data.dir);
+    /// Atomically writes the versioned model to path
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_versioned(self, path)
+    }
 
-        // create save file if it doesn't exist
-        File::create(&abs_path)
-            .expect("couldn't create model save file!");
+    /// Writes the versioned model to writer
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<()> {
+        format::write_versioned(self, writer)
+    }
 
-        // write Network contents to file
-        std::fs::write(&abs_path, net)
-            .expect("couldn't write model to file!");
+    /// Atomically writes the model to path in the compact binary format
+    pub fn save_binary_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::write_atomic(path, &self.to_binary()?)
     }
 
-    pub fn from_file(path: &str) -> Self {
-        let work_dir = std::env::current_dir()
-            .expect("invalid working directory!");
+    /// Writes the model to writer in the compact binary format
+    pub fn write_binary_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(&self.to_binary()?)?;
+        Ok(())
+    }
 
-        // create reference to absolute path
-        let abs_path = work_dir.join(&path);
+    fn to_binary(&self) -> Result<Vec<u8>> {
+        let meta = serde_json::to_vec(&self.data)?;
+        let masks = self.masks.as_ref().map(|masks| masks.buf.as_slice());
+        Ok(format::to_binary(&self.data.form, &meta, &self.weights.buf, &self.biases.buf, masks))
+    }
 
-        let net = std::fs::read_to_string(&abs_path)
-            .expect("couldn't read model file!");
-        
-        serde_json::from_str(&net)
-            .expect("couldn't convert from model string!")
+    fn from_binary(bytes: &[u8]) -> Result<Self> {
+        let binary = format::from_binary(bytes)?;
+        let data: HyperData<L> = serde_json::from_slice(&binary.meta)?;
+
+        if data.form != binary.form {
+            return Err(NetError::Corrupt("header form doesn't match hyper data"))
+        }
+
+        let mut net = Self::try_from_parts(data)?;
+        net.weights = Array::from_buf(binary.weights);
+        net.biases = Array::from_buf(binary.biases);
+        net.masks = binary.masks.map(Array::from_buf);
+
+        Ok(net)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Self {
+        Self::try_from_file(path)
+            .unwrap_or_else(|err| panic!("couldn't load model: {}", err))
+    }
+
+    /// Reads a model of any known format version from path
+    pub fn try_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_from(format::open(path)?)
+    }
+
+    /// Reads a JSON or binary model of any known format version from reader
+    pub fn read_from<R: Read>(reader: R) -> Result<Self> {
+        let bytes = format::read_all(reader)?;
+
+        let net = if format::is_binary(&bytes) {
+            Self::from_binary(&bytes)?
+        } 
+        else {
+            format::from_versioned(&bytes)?
+        };
+
+        net.check_form()?;
+
+        Ok(net)
+    }
+
+    /// Fails if the parameters or training buffers don't match the network form
+    fn check_form(&self) -> Result<()> {
+        let form = &self.data.form;
+
+        if form.len() != L {
+            return Err(NetError::LayerCount { expected: L, found: form.len() })
+        }
+
+        let vector = |l: usize| (form[l+1], 1);
+        let matrix = |l: usize| (form[l+1], form[l]);
+
+        format::check_form(form, self.weights.shapes(), self.biases.shapes())?;
+
+        if let Some(masks) = &self.masks {
+            format::check_shapes(masks.shapes(), L-1, matrix)?;
+        }
+
+        // the activations include the input layer
+        format::check_shapes(self.acts.shapes(), L, |l| (form[l], 1))?;
+
+        for buffer in [&self.sums, &self.err, &self.acc_err] {
+            format::check_shapes(buffer.shapes(), L-1, vector)?;
+        }
+
+        for buffer in [&self.w_err, &self.acc_w_err] {
+            format::check_shapes(buffer.shapes(), L-1, matrix)?;
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn check_training(&self, inputs: &[Vector], targets: &[Vector], valid: Option<(&[Vector], &[Vector])>) -> Result<()> {
+        self.data.check_training(inputs, targets, valid)
+    }
+
+    pub(crate) fn check_data(&self, inputs: &[Vector], targets: &[Vector]) -> Result<()> {
+        self.data.check_data(inputs, targets)
+    }
+
+    /// Atomically writes the model with its in-flight training progress
+    /// to path, as a checkpoint that only `resume_from` reads
+    pub fn save_checkpoint_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_versioned(&Checkpointed { net: self, progress: &self.progress }, path)
+    }
+
+    /// Loads a checkpoint saved during training, the next call to `train`
+    /// with the same data and epochs continues from its epoch and batch
+    pub fn resume_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let bytes = format::read_all(format::open(path)?)?;
+        let Checkpointed { mut net, progress }: Checkpointed<Self, Progress> = format::from_versioned(&bytes)?;
+
+        net.check_form()?;
+        net.progress = progress;
+
+        Ok(net)
+    }
+
+    pub fn progress(&self) -> &Progress {
+        &self.progress
     }
 
     pub fn stats(&self) -> &HyperData<L> {
@@ -270,20 +587,45 @@ impl<const L: usize> Net<L> {
         self.acc_samples += 1;
     }
 
-    pub fn apply_gradient(&mut self, sample_size: usize) {
+    /// Applies the accumulated gradient, returning its norm before clipping
+    pub fn apply_gradient(&mut self, sample_size: usize) -> f32 {
+        // frozen layers neither change nor count towards the clipped norm
+        for &l in self.data.frozen.iter().filter(|l| **l < L-1) {
+            self.acc_err[l].fill_zero();
+            self.acc_w_err[l].fill_zero();
+        }
+
+        let norm = self.clip_gradient(sample_size, self.data.clip);
+
         // coefficient of learn rate
         let learn_rate = self.data.learn_rate / sample_size as f32;
 
         // apply stochastic error gradient 
-        for j in 0..L-1 {
+        for j in (0..L-1).filter(|j| !self.data.frozen.contains(j)) {
             self.biases[j].add_eq(&self.acc_err[j].scale(learn_rate));
             self.weights[j].add_eq(&self.acc_w_err[j].scale(learn_rate));
         }
+
+        // keep pruned weights at zero
+        if let Some(masks) = &self.masks {
+            for j in 0..L-1 {
+                self.weights[j].dot_eq(&masks[j]);
+            }
+        }
+
+        norm
     }
 
     pub fn forward_prop(&mut self, input: &Vector) -> &Vector {
-        if input.row() != self.data.form[0] {
-            panic!("expected data with {} rows, found shape {:?}, !", L, input.shape())
+        match self.try_forward_prop(input) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    pub fn try_forward_prop(&mut self, input: &Vector) -> Result<&Vector> {
+        if input.shape() != (self.data.form[0], 1) {
+            return Err(NetError::Shape { expected: (self.data.form[0], 1), found: input.shape() })
         }
 
         self.acts[0] = input.clone();
@@ -291,10 +633,11 @@ impl<const L: usize> Net<L> {
         for l in 0..L-1 {                  
             self.weights[l].mul_to(&self.acts[l], &mut self.sums[l]);
             self.sums[l].add_eq(&self.biases[l]);      
-            self.acts[l+1] = self.sums[l].map(|n| self.data.act(n));
+            self.acts[l+1] = self.sums[l].clone();
+            self.data.activate_eq(l, &mut self.acts[l+1]);
         }
 
-        &self.acts[Back(0)]
+        Ok(&self.acts[Back(0)])
     }
 
     pub fn back_prop(&mut self, input: &Vector, target: &Vector) {        
@@ -304,11 +647,26 @@ impl<const L: usize> Net<L> {
         // propagate and store input
         self.forward_prop(input);
 
-        // error_L = cost' ( y - a_L ) . sum_L
-        self.err[Back(0)] = target.sub(&self.acts[Back(0)]);
-        self.err[Back(0)].map_eq(|n| self.data.d_cost(n));
-        self.err[Back(0)].dot_eq(&self.sums[Back(0)].map(|n| self.data.d_act(n)));
+        self.output_error(target);
+        self.back_prop_error();
+    }
+
+    /// Stores the output layer error of the last propagated input
+    pub(crate) fn output_error(&mut self, target: &Vector) {
+        if self.data.out == Output::Sigmoid && self.data.cost == Cost::BinCross {
+            // error_L = y - a_L, the sigmoid derivative cancels the cross-entropy
+            // denominator, which stays exact on saturated outputs
+            self.err[Back(0)] = target.sub(&self.acts[Back(0)]);
+        }
+        else {
+            // error_L = cost' ( a_L, y ) . out' ( sum_L )
+            self.err[Back(0)] = self.data.d_cost(&self.acts[Back(0)], target);
+            self.data.out.deriv_eq(self.data.act, &self.sums[Back(0)], &self.acts[Back(0)], &mut self.err[Back(0)]);
+        }
+    }
 
+    /// Propagates the output layer error back through the hidden layers
+    pub(crate) fn back_prop_error(&mut self) {
         for l in 0..L-1 {
             // weight_l = error_l x activations_l-1 ^ T
             self.err[Back(l)].mul_t2_to(&self.acts[Back(1+l)], &mut self.w_err[Back(l)]);
@@ -325,55 +683,340 @@ impl<const L: usize> Net<L> {
         }
     }
 
-    pub fn train(&mut self, inputs: &[Vector], targets: &[Vector], epochs: usize) {
-        if inputs.len() != targets.len() {
-            panic!("unequal amounts of input ({}) and output ({}) data!", inputs.len(), targets.len())
+    pub fn train(&mut self, inputs: &[Vector], targets: &[Vector], epochs: usize) -> History {
+        let mut logger = Logger::new(self.data.stat_epoch, self.data.stat_error);
+        self.train_with(inputs, targets, None, epochs, &mut [&mut logger])
+    }
+
+    pub fn try_train(&mut self, inputs: &[Vector], targets: &[Vector], epochs: usize) -> Result<History> {
+        self.check_training(inputs, targets, None)?;
+        Ok(self.train(inputs, targets, epochs))
+    }
+
+    /// Trains while evaluating the validation set every epoch, stopping
+    /// after `patience` epochs without improvement of the validation loss
+    /// and restoring the best weights seen
+    pub fn train_validated(
+        &mut self, 
+        inputs: &[Vector], 
+        targets: &[Vector], 
+        valid_inputs: &[Vector], 
+        valid_targets: &[Vector], 
+        epochs: usize
+    ) -> History {
+        let mut logger = Logger::new(self.data.stat_epoch, self.data.stat_error);
+        self.train_with(inputs, targets, Some((valid_inputs, valid_targets)), epochs, &mut [&mut logger])
+    }
+
+    pub fn try_train_validated(
+        &mut self, 
+        inputs: &[Vector], 
+        targets: &[Vector], 
+        valid_inputs: &[Vector], 
+        valid_targets: &[Vector], 
+        epochs: usize
+    ) -> Result<History> {
+        self.check_training(inputs, targets, Some((valid_inputs, valid_targets)))?;
+        Ok(self.train_validated(inputs, targets, valid_inputs, valid_targets, epochs))
+    }
+
+    /// Trains with an optional validation set, invoking the given
+    /// callbacks on train, epoch and batch events
+    pub fn train_with(
+        &mut self, 
+        inputs: &[Vector], 
+        targets: &[Vector], 
+        valid: Option<(&[Vector], &[Vector])>, 
+        epochs: usize,
+        callbacks: &mut [&mut dyn Callback<L>]
+    ) -> History {
+        self.train_loop(inputs, targets, None, valid, epochs, callbacks)
+    }
+
+    /// Trains on targets, blended with the soft targets of a teacher if given
+    pub(crate) fn train_loop(
+        &mut self, 
+        inputs: &[Vector], 
+        targets: &[Vector], 
+        soft: Option<&SoftTargets>,
+        valid: Option<(&[Vector], &[Vector])>, 
+        epochs: usize,
+        callbacks: &mut [&mut dyn Callback<L>]
+    ) -> History {
+        if let Err(err) = self.check_training(inputs, targets, valid) {
+            panic!("{}", err)
         }
 
-        for epoch in 0..epochs {
-            if self.data.stat_epoch {
-                println!("epoch {} of {}", epoch+1, epochs);
+        let mut stop = dispatch(callbacks, |c| c.on_train_begin(self));
+
+        'train: while self.progress.epoch < epochs && !stop {
+            let epoch = self.progress.epoch;
+
+            // a resumed epoch has already begun
+            if self.progress.batch == 0 && dispatch(callbacks, |c| c.on_epoch_begin(epoch, epochs, self)) {
+                break
+            }
+
+            let start = Instant::now();
+            let time = self.progress.time;
+
+            let mut rng = self.progress.rng.clone();
+            let order = if self.data.shuffle {
+                rng.permutation(inputs.len())
             }
+            else {
+                (0..inputs.len()).collect()
+            };
 
             self.clear_accumulation_data();
+
+            let mut batch_loss = 0.;
     
-            for i in 0..inputs.len() {
-                self.back_prop(&inputs[i], &targets[i]);
+            for (n, &i) in order.iter().enumerate().skip(self.progress.samples) {
+                match soft {
+                    Some(soft) => self.back_prop_distilled(&inputs[i], &targets[i], soft, i),
+                    None => self.back_prop(&inputs[i], &targets[i])
+                }
                 self.accumulate_error();
-    
-                if self.acc_samples == self.data.batch_size {
-                    self.apply_gradient(self.data.batch_size);
+
+                let sample_loss = self.output_loss(&targets[i]);
+                self.progress.loss += sample_loss;
+                self.progress.samples += 1;
+                batch_loss += sample_loss;
+
+                if self.acc_samples == self.data.batch_size || n == inputs.len()-1 {
+                    let batch_size = self.acc_samples;
+
+                    self.progress.grad_norm += self.apply_gradient(batch_size);
                     self.clear_accumulation_data();
+
+                    let batch = self.progress.batch;
+                    self.progress.batch += 1;
+                    self.progress.time = time + start.elapsed().as_secs_f32();
+
+                    let batch_loss = std::mem::take(&mut batch_loss) / batch_size as f32;
+                    // an interrupted epoch is left unfinished so checkpoints resume within it
+                    if dispatch(callbacks, |c| c.on_batch_end(epoch, batch, batch_loss, self)) {
+                        break 'train
+                    }
                 }
             }
-    
-            if self.acc_samples != 0 {
-                self.apply_gradient(self.acc_samples);
-                self.clear_accumulation_data();
+
+            // the loss is a running mean over the epoch, while the scores
+            // are evaluated with the parameters after the last update
+            let (accuracy, regression) = self.scores(inputs, targets);
+
+            let mut metrics = Metrics {
+                epoch,
+                loss: self.progress.loss / self.progress.samples.max(1) as f32,
+                accuracy: accuracy.unwrap_or(0.),
+                valid_loss: None,
+                valid_accuracy: None,
+                regression,
+                valid_regression: None,
+                grad_norm: self.progress.grad_norm / self.progress.batch.max(1) as f32,
+                learn_rate: self.data.learn_rate,
+                time: 0.
+            };
+
+            let mut improved = false;
+
+            if let Some((valid_inputs, valid_targets)) = valid {
+                let valid_loss = self.loss(valid_inputs, valid_targets);
+
+                metrics.valid_loss = Some(valid_loss);
+                (metrics.valid_accuracy, metrics.valid_regression) = self.scores(valid_inputs, valid_targets);
+
+                if self.progress.best.as_ref().is_none_or(|best| valid_loss < best.loss) {
+                    self.progress.best = Some(Best { 
+                        loss: valid_loss, 
+                        weights: self.weights.clone(), 
+                        biases: self.biases.clone() 
+                    });
+                    self.progress.stale = 0;
+                    improved = true;
+                }
+                else {
+                    self.progress.stale += 1;
+                }
             }
 
+            metrics.time = time + start.elapsed().as_secs_f32();
+
+            self.progress.history.push(metrics.clone());
+            self.progress.next_epoch(rng);
 
-            if self.data.stat_error {
-                let accuracy = self.accuracy(inputs, targets);
-                println!("accuracy of {}", accuracy);
+            // a failed save leaves training running with the previous best file
+            if improved && self.data.save_best {
+                if let Err(err) = self.try_save() {
+                    eprintln!("couldn't save best model: {}", err);
+                }
             }
+
+            stop |= dispatch(callbacks, |c| c.on_epoch_end(&metrics, self));
+            stop |= self.data.patience.is_some_and(|patience| self.progress.stale >= patience);
         }
+
+        // a run stopped within an epoch keeps its progress, so that
+        // the next call to `train` continues from its last batch
+        let history = if self.progress.batch != 0 {
+            self.progress.history.clone()
+        }
+        else {
+            let rng = self.progress.rng.clone();
+            let progress = std::mem::replace(&mut self.progress, Progress { rng, ..Progress::default() });
+
+            // restore best validated parameters
+            if let Some(best) = progress.best {
+                self.weights = best.weights;
+                self.biases = best.biases;
+            }
+
+            progress.history
+        };
+
+        dispatch(callbacks, |c| {
+            c.on_train_end(self);
+            Signal::Continue
+        });
+
+        history
+    }
+
+    /// Returns the accuracy, or the regression scores for regression tasks
+    fn scores(&self, inputs: &[Vector], targets: &[Vector]) -> (Option<f32>, Option<RegressionScores>) {
+        match self.data.task {
+            Task::Classification => (Some(self.accuracy(inputs, targets)), None),
+            Task::Regression => (None, Some(self.regression(inputs, targets).total)),
+            Task::MultiLabel => (Some(self.evaluate_labels(inputs, targets, &[]).subset_accuracy), None)
+        }
+    }
+
+    /// Returns the cost of the last propagated output
+    pub(crate) fn output_loss(&self, target: &Vector) -> f32 {
+        self.data.cost(&self.acts[Back(0)], target)
     }
 
-    pub fn accuracy(&mut self, inputs: &[Vector], outs: &[Vector]) -> f32 {
+    /// Returns the mean cost over the given data
+    pub fn loss(&self, inputs: &[Vector], outs: &[Vector]) -> f32 {
         if inputs.len() != outs.len() {
             panic!("unequal amounts of input ({}) and output ({}) data!", inputs.len(), outs.len())
         }
 
+        let mut scratch = Scratch::new();
+        let mut total = 0.;
+
+        for i in 0..inputs.len() {
+            let out = self.predict_with(&inputs[i], &mut scratch);
+
+            total += self.data.cost(out, &outs[i]);
+        }
+
+        total / inputs.len() as f32
+    }
+
+    pub fn accuracy(&self, inputs: &[Vector], outs: &[Vector]) -> f32 {
+        self.try_accuracy(inputs, outs)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_accuracy(&self, inputs: &[Vector], outs: &[Vector]) -> Result<f32> {
+        self.check_data(inputs, outs)?;
+
+        let mut scratch = Scratch::new();
         let mut correct = 0;
 
         for i in 0..inputs.len() {
-            let out = self.forward_prop(&inputs[i]);
+            let out = self.predict_with(&inputs[i], &mut scratch);
             if out.hot() == outs[i].hot() {
                 correct += 1;
             }
         }
 
-        correct as f32 / inputs.len() as f32
+        Ok(correct as f32 / inputs.len() as f32)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    use crate::callback::{Checkpoint, Signal};
+
+    /// Stops training once the given batch of an epoch has ended
+    struct StopAt {
+        epoch: usize,
+
+        batch: usize
     }
-}
\ No newline at end of file
+
+    impl<const L: usize> Callback<L> for StopAt {
+        fn on_batch_end(&mut self, epoch: usize, batch: usize, _loss: f32, _net: &mut Net<L>) -> Signal {
+            if (epoch, batch) == (self.epoch, self.batch) { Signal::Stop } else { Signal::Continue }
+        }
+    }
+
+    fn data() -> (Vec<Vector>, Vec<Vector>) {
+        let inputs = (0..12).map(|i| Vector::from_arr([(i as f32 * 0.7).sin(), (i as f32 * 0.3).cos()])).collect();
+        let targets = (0..12).map(|i| Vector::one_hot(2, i % 2)).collect();
+
+        (inputs, targets)
+    }
+
+    /// Returns a fresh net with its clone, sharing the shuffle seed
+    fn nets() -> (Net<3>, Net<3>) {
+        let net = Net::new([2, 4, 2]).with_batch_size(4).with_shuffle(true).build();
+        (net.clone(), net)
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("net-rs-{}-{}.json", name, std::process::id()))
+    }
+
+    #[test]
+    fn resume_within_an_epoch_matches_an_uninterrupted_run() {
+        let (inputs, targets) = data();
+        let (mut full, mut stopped) = nets();
+
+        full.train_with(&inputs, &targets, None, 4, &mut []);
+        stopped.train_with(&inputs, &targets, None, 4, &mut [&mut StopAt { epoch: 2, batch: 1 }]);
+
+        let path = temp_path("resume-batch");
+        stopped.save_checkpoint_to(&path).unwrap();
+        let resumed = Net::<3>::resume_from(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let mut resumed = resumed.unwrap();
+        assert_eq!((resumed.progress().epoch, resumed.progress().batch), (2, 2));
+
+        let history = resumed.train_with(&inputs, &targets, None, 4, &mut []);
+
+        assert_eq!(history.len(), 4);
+        assert!(full.weights.buf == resumed.weights.buf && full.biases.buf == resumed.biases.buf);
+    }
+
+    #[test]
+    fn resume_from_an_epoch_checkpoint_matches_an_uninterrupted_run() {
+        let (inputs, targets) = data();
+        let (mut full, mut stopped) = nets();
+
+        let path = temp_path("resume-epoch");
+        let mut checkpoint = Checkpoint::new(path.to_str().unwrap(), false);
+
+        full.train_with(&inputs, &targets, None, 4, &mut []);
+        stopped.train_with(&inputs, &targets, None, 4, &mut [&mut checkpoint, &mut StopAt { epoch: 2, batch: 0 }]);
+
+        let resumed = Net::<3>::resume_from(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        let mut resumed = resumed.unwrap();
+        assert_eq!((resumed.progress().epoch, resumed.progress().batch), (2, 0));
+
+        let history = resumed.train_with(&inputs, &targets, None, 4, &mut []);
+
+        assert_eq!(history.len(), 4);
+        assert!(full.weights.buf == resumed.weights.buf && full.biases.buf == resumed.biases.buf);
+    }
+}
@@ -0,0 +1,110 @@
+use std::cell::RefCell;
+
+use crate::error::{NetError, Result};
+use crate::linalg::*;
+use crate::net::Net;
+
+thread_local! {
+    // per thread buffers backing `Net::predict`
+    static SCRATCH: RefCell<Scratch> = RefCell::new(Scratch::new());
+}
+
+/// Caller-owned activation buffers for immutable prediction,
+/// resized on demand to fit whichever network uses them
+#[derive(Clone, Default)]
+pub struct Scratch {
+    // layer activations buffer
+    acts: Vec<Vector>
+}
+
+impl Scratch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn fit(&mut self, form: &[usize]) {
+        self.acts.resize_with(form.len()-1, || Vector::from_zeros(0));
+
+        for (act, size) in self.acts.iter_mut().zip(&form[1..]) {
+            if act.row() != *size {
+                *act = Vector::from_zeros(*size);
+            }
+        }
+    }
+}
+
+impl<const L: usize> Net<L> {
+    /// Propagates input without touching the training buffers,
+    /// so a shared net can predict from many threads
+    pub fn predict(&self, input: &Vector) -> Vector {
+        match self.try_predict(input) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    pub fn try_predict(&self, input: &Vector) -> Result<Vector> {
+        SCRATCH.with(|scratch| {
+            self.try_predict_with(input, &mut scratch.borrow_mut()).cloned()
+        })
+    }
+
+    /// Propagates input using caller-owned buffers
+    pub fn predict_with<'a>(&self, input: &Vector, scratch: &'a mut Scratch) -> &'a Vector {
+        match self.try_predict_with(input, scratch) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    pub fn try_predict_with<'a>(&self, input: &Vector, scratch: &'a mut Scratch) -> Result<&'a Vector> {
+        self.propagate(input, scratch, true)
+    }
+
+    /// Propagates input, returning the output layer sums
+    /// before the output function is applied
+    pub fn predict_logits(&self, input: &Vector) -> Vector {
+        match self.try_predict_logits(input) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    pub fn try_predict_logits(&self, input: &Vector) -> Result<Vector> {
+        SCRATCH.with(|scratch| {
+            self.propagate(input, &mut scratch.borrow_mut(), false).cloned()
+        })
+    }
+
+    fn propagate<'a>(&self, input: &Vector, scratch: &'a mut Scratch, output: bool) -> Result<&'a Vector> {
+        if input.shape() != (self.data.form[0], 1) {
+            return Err(NetError::Shape { expected: (self.data.form[0], 1), found: input.shape() })
+        }
+
+        scratch.fit(&self.data.form);
+
+        for l in 0..L-1 {
+            let (prev, next) = scratch.acts.split_at_mut(l);
+            let act = if l == 0 { input } else { &prev[l-1] };
+
+            self.weights[l].mul_to(act, &mut next[0]);
+            next[0].add_eq(&self.biases[l]);
+
+            if output || l != L-2 {
+                self.data.activate_eq(l, &mut next[0]);
+            }
+        }
+
+        Ok(&scratch.acts[L-2])
+    }
+
+    /// Propagates every input, sharing one set of buffers
+    pub fn predict_batch(&self, inputs: &[Vector]) -> Vec<Vector> {
+        let mut scratch = Scratch::new();
+
+        inputs
+            .iter()
+            .map(|input| self.predict_with(input, &mut scratch).clone())
+            .collect()
+    }
+}
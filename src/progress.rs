@@ -0,0 +1,104 @@
+use serde_derive::{Serialize, Deserialize};
+
+use crate::array::Array;
+use crate::history::History;
+use crate::linalg::{Matrix, Vector};
+
+/// Serializable position of an in-flight training run, saved next to
+/// the `Net` in checkpoints so a run can resume exactly where it stopped
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Progress {
+    // current epoch
+    pub epoch: usize,
+
+    // finished batches of the current epoch
+    pub batch: usize,
+
+    // metrics of the finished epochs
+    pub history: History,
+
+    // finished samples of the current epoch
+    pub(crate) samples: usize,
+
+    // summed cost of the current epoch
+    pub(crate) loss: f32,
+
+    // summed gradient norms of the current epoch
+    pub(crate) grad_norm: f32,
+
+    // training seconds of the current epoch
+    pub(crate) time: f32,
+
+    // shuffle state at the start of the current epoch
+    pub(crate) rng: Shuffler,
+
+    // lowest validation loss with its parameters
+    pub(crate) best: Option<Best>,
+
+    // epochs without validation improvement
+    pub(crate) stale: usize
+}
+
+impl Progress {
+    /// Returns whether a training run was interrupted part way
+    pub fn in_flight(&self) -> bool {
+        self.epoch != 0 || self.batch != 0
+    }
+
+    /// Clears the current epoch's running sums for the next epoch
+    pub(crate) fn next_epoch(&mut self, rng: Shuffler) {
+        self.epoch += 1;
+        self.batch = 0;
+        self.samples = 0;
+        self.loss = 0.;
+        self.grad_norm = 0.;
+        self.time = 0.;
+        self.rng = rng;
+    }
+}
+
+/// Parameters of the best validated epoch
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Best {
+    pub loss: f32,
+    pub weights: Array<Matrix>,
+    pub biases: Array<Vector>
+}
+
+/// Xorshift generator whose state is serialized with the training progress
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Shuffler {
+    state: u64
+}
+
+impl Default for Shuffler {
+    fn default() -> Self {
+        Self::new(rand::random())
+    }
+}
+
+impl Shuffler {
+    pub fn new(seed: u64) -> Self {
+        // xorshift state must be non-zero
+        Self { state: seed.max(1) }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// Returns a Fisher-Yates shuffled order of 0..len
+    pub fn permutation(&mut self, len: usize) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..len).collect();
+
+        for i in (1..len).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            order.swap(i, j);
+        }
+
+        order
+    }
+}
@@ -0,0 +1,387 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use serde_derive::{Serialize, Deserialize};
+
+use crate::array::Array;
+use crate::classify::{to_probabilities, Classifier};
+use crate::error::{NetError, Result};
+use crate::format;
+use crate::history::History;
+use crate::linalg::*;
+use crate::net::Net;
+use crate::step::{Activation, Output};
+
+/// Target fraction of weights pruned by magnitude
+#[derive(Clone, Debug)]
+pub enum Sparsity {
+    // fraction of all weights, pruning the smallest across every layer
+    Global(f32),
+
+    // fraction of each layer's weights, a single value applies to every layer
+    PerLayer(Vec<f32>)
+}
+
+impl Sparsity {
+    /// Returns the sparsity scaled by a fraction of the target
+    fn scale(&self, fraction: f32) -> Self {
+        match self {
+            Sparsity::Global(n) => Sparsity::Global(n * fraction),
+            Sparsity::PerLayer(ns) => Sparsity::PerLayer(ns.iter().map(|n| n * fraction).collect())
+        }
+    }
+}
+
+impl<const L: usize> Net<L> {
+    /// Zeroes the smallest weights to the target sparsity and masks
+    /// them, so that training keeps them at zero
+    pub fn prune(&mut self, sparsity: &Sparsity) {
+        self.try_prune(sparsity)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_prune(&mut self, sparsity: &Sparsity) -> Result<()> {
+        let masks = match sparsity {
+            Sparsity::Global(fraction) => {
+                let weights: Vec<&Matrix> = self.weights.buf.iter().collect();
+                magnitude_masks(&weights, *fraction)
+            }
+            Sparsity::PerLayer(fractions) => {
+                if fractions.len() != 1 && fractions.len() != L-1 {
+                    return Err(NetError::LayerCount { expected: L-1, found: fractions.len() })
+                }
+
+                (0..L-1)
+                    .flat_map(|l| {
+                        let fraction = fractions[l.min(fractions.len()-1)];
+                        magnitude_masks(&[&self.weights[l]], fraction)
+                    })
+                    .collect()
+            }
+        };
+
+        for (weights, mask) in self.weights.buf.iter_mut().zip(&masks) {
+            weights.dot_eq(mask);
+        }
+
+        self.masks = Some(Array::from_buf(masks));
+
+        Ok(())
+    }
+
+    /// Prunes gradually over cycles towards the target sparsity,
+    /// fine-tuning for the given epochs after every pruning step
+    pub fn prune_and_fine_tune(
+        &mut self,
+        inputs: &[Vector],
+        targets: &[Vector],
+        sparsity: &Sparsity,
+        cycles: usize,
+        epochs: usize
+    ) -> Vec<History> {
+        let cycles = cycles.max(1);
+
+        (1..=cycles)
+            .map(|cycle| {
+                self.prune(&sparsity.scale(cycle as f32 / cycles as f32));
+                self.train(inputs, targets, epochs)
+            })
+            .collect()
+    }
+
+    /// Removes the pruning masks, letting pruned weights train again
+    pub fn clear_masks(&mut self) {
+        self.masks = None;
+    }
+
+    /// Returns the fraction of zero weights
+    pub fn sparsity(&self) -> f32 {
+        let (zeros, total) = self.weights
+            .buf
+            .iter()
+            .fold((0, 0), |(zeros, total), w| (zeros + count_zeros(w), total + w.buf().len()));
+
+        zeros as f32 / total.max(1) as f32
+    }
+
+    /// Returns the fraction of zero weights of every layer
+    pub fn layer_sparsity(&self) -> Vec<f32> {
+        self.weights
+            .buf
+            .iter()
+            .map(|w| count_zeros(w) as f32 / w.buf().len().max(1) as f32)
+            .collect()
+    }
+
+    /// Exports the network with its weights in a compressed sparse row format
+    pub fn to_sparse(&self) -> Sparse {
+        Sparse {
+            form: self.data.form.clone(),
+            weights: self.weights.buf.iter().map(SparseMatrix::from_dense).collect(),
+            biases: self.biases.clone(),
+            act: self.data.act,
+            out: self.data.out
+        }
+    }
+}
+
+/// Returns masks zeroing the given fraction of smallest magnitude weights
+fn magnitude_masks(weights: &[&Matrix], fraction: f32) -> Vec<Matrix> {
+    let mut order: Vec<(usize, usize, f32)> = weights
+        .iter()
+        .enumerate()
+        .flat_map(|(l, w)| w.buf().iter().enumerate().map(move |(i, n)| (l, i, n.abs())))
+        .collect();
+
+    order.sort_by(|a, b| a.2.total_cmp(&b.2));
+
+    let pruned = (fraction.clamp(0., 1.) * order.len() as f32).round() as usize;
+
+    let mut masks: Vec<Matrix> = weights
+        .iter()
+        .map(|w| Matrix::from_fill((w.row(), w.col()), 1.))
+        .collect();
+
+    for (l, i, _) in order.into_iter().take(pruned) {
+        masks[l].buf_mut()[i] = 0.;
+    }
+
+    masks
+}
+
+fn count_zeros(weights: &Matrix) -> usize {
+    weights.buf().iter().filter(|n| **n == 0.).count()
+}
+
+/// Matrix in compressed sparse row format holding only non-zero values
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SparseMatrix {
+    row: usize,
+
+    col: usize,
+
+    // start of every row in cols and values, followed by their length
+    row_ptr: Vec<u32>,
+
+    // column of every value
+    cols: Vec<u32>,
+
+    values: Vec<f32>
+}
+
+impl SparseMatrix {
+    pub fn from_dense(dense: &Matrix) -> Self {
+        let mut row_ptr = Vec::with_capacity(dense.row() + 1);
+        let mut cols = Vec::new();
+        let mut values = Vec::new();
+
+        for r in 0..dense.row() {
+            row_ptr.push(values.len() as u32);
+
+            for c in 0..dense.col() {
+                let n = dense[(r, c)];
+
+                if n != 0. {
+                    cols.push(c as u32);
+                    values.push(n);
+                }
+            }
+        }
+
+        row_ptr.push(values.len() as u32);
+
+        Self { row: dense.row(), col: dense.col(), row_ptr, cols, values }
+    }
+
+    pub fn shape(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// Returns the number of stored non-zero values
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Multiplies the sparse matrix by vector into buffer
+    pub fn mul_to<'a>(&self, rhs: &Vector, buf: &'a mut Vector) -> &'a mut Vector {
+        if self.col != rhs.row() {
+            panic!("cannot multiply {:?} by {:?}", self.shape(), rhs.shape())
+        }
+
+        for r in 0..self.row {
+            let (start, end) = (self.row_ptr[r] as usize, self.row_ptr[r+1] as usize);
+
+            buf[r] = self.cols[start..end]
+                .iter()
+                .zip(&self.values[start..end])
+                .map(|(c, n)| n * rhs[*c as usize])
+                .sum();
+        }
+
+        buf
+    }
+
+    /// Fails if the row pointers and columns don't index the values
+    fn check(&self) -> Result<()> {
+        let ordered = self.row_ptr.len() == self.row + 1
+            && self.row_ptr.first() == Some(&0)
+            && self.row_ptr.windows(2).all(|w| w[0] <= w[1])
+            && self.row_ptr.last().is_some_and(|end| *end as usize == self.values.len())
+            && self.cols.len() == self.values.len()
+            && self.cols.iter().all(|c| (*c as usize) < self.col);
+
+        if !ordered {
+            return Err(NetError::Corrupt("invalid sparse matrix indices"))
+        }
+
+        Ok(())
+    }
+}
+
+/// Inference-only network of a pruned `Net`, skipping the pruned weights
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Sparse {
+    // layer sizes
+    form: Vec<usize>,
+
+    weights: Vec<SparseMatrix>,
+
+    biases: Array<Vector>,
+
+    // step function
+    act: Activation,
+
+    // output layer function
+    #[serde(default)]
+    out: Output
+}
+
+impl Sparse {
+    pub fn form(&self) -> &[usize] {
+        &self.form
+    }
+
+    pub fn act(&self) -> Activation {
+        self.act
+    }
+
+    pub fn out(&self) -> Output {
+        self.out
+    }
+
+    pub fn weights(&self) -> &[SparseMatrix] {
+        &self.weights
+    }
+
+    /// Returns the number of stored non-zero weights
+    pub fn nnz(&self) -> usize {
+        self.weights.iter().map(|w| w.nnz()).sum()
+    }
+
+    pub fn forward_prop(&self, input: &Vector) -> Vector {
+        match self.try_forward_prop(input) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    pub fn try_forward_prop(&self, input: &Vector) -> Result<Vector> {
+        if input.shape() != (self.form[0], 1) {
+            return Err(NetError::Shape { expected: (self.form[0], 1), found: input.shape() })
+        }
+
+        let mut act = input.clone();
+
+        for l in 0..self.weights.len() {
+            let mut sum = Vector::from_zeros(self.form[l+1]);
+
+            self.weights[l].mul_to(&act, &mut sum);
+            sum.add_eq(&self.biases[l]);
+
+            if l == self.weights.len()-1 {
+                self.out.value_eq(self.act, &mut sum);
+            }
+            else {
+                sum.map_eq(|n| self.act.value(n));
+            }
+
+            act = sum;
+        }
+
+        Ok(act)
+    }
+
+    /// Atomically writes the versioned model to path
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_versioned(self, path)
+    }
+
+    /// Writes the versioned model to writer
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<()> {
+        format::write_versioned(self, writer)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Self {
+        Self::try_from_file(path)
+            .unwrap_or_else(|err| panic!("couldn't load sparse model: {}", err))
+    }
+
+    pub fn try_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_from(format::open(path)?)
+    }
+
+    pub fn read_from<R: Read>(reader: R) -> Result<Self> {
+        let bytes = format::read_all(reader)?;
+
+        let model: Self = format::from_versioned(&bytes)?;
+        model.check_form()?;
+
+        Ok(model)
+    }
+
+    /// Fails if the layer buffers don't match the model form
+    fn check_form(&self) -> Result<()> {
+        let weights = self.weights.iter().map(SparseMatrix::shape);
+        format::check_form(&self.form, weights, self.biases.shapes())?;
+
+        for weights in self.weights.iter() {
+            weights.check()?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Classifier for Sparse {
+    fn try_output(&self, input: &Vector) -> Result<Vector> {
+        self.try_forward_prop(input)
+    }
+
+    fn normalize_output(&self, out: &Vector) -> Vector {
+        to_probabilities(out, self.act, self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_reload_keeps_pruned_weights_pruned() {
+        let inputs: Vec<Vector> = (0..16).map(|i| Vector::from_arr([(i as f32).sin(), (i as f32).cos(), 0.5])).collect();
+        let targets: Vec<Vector> = (0..16).map(|i| Vector::one_hot(2, i % 2)).collect();
+
+        let mut net = Net::new([3, 8, 2]).with_learn_rate(0.5).build();
+        net.prune(&Sparsity::Global(0.5));
+
+        let mut bytes = Vec::new();
+        net.write_binary_to(&mut bytes).unwrap();
+
+        let mut loaded = Net::<3>::read_from(bytes.as_slice()).unwrap();
+        let sparsity = loaded.sparsity();
+        loaded.train(&inputs, &targets, 3);
+
+        assert!(sparsity >= 0.5);
+        assert_eq!(loaded.sparsity(), sparsity);
+    }
+}
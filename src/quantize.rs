@@ -0,0 +1,446 @@
+use std::fmt;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use serde_derive::{Serialize, Deserialize};
+
+use crate::classify::{to_probabilities, Classifier};
+use crate::error::{NetError, Result};
+use crate::format::{self, ByteReader};
+use crate::linalg::*;
+use crate::net::Net;
+use crate::step::{Activation, Output};
+
+/// QUANTIZED FORMAT:
+///
+/// all values are little-endian
+///
+/// [offset]   [type]           [description]
+/// 0000       [u8; 4]          magic "NETQ"
+/// 0004       u32              quantized format version
+/// 0008       u32              # layers
+/// 0012       u32              layer size
+/// ...
+/// xxxx       u32              # meta bytes
+/// xxxx       [u8]             meta data as JSON
+/// xxxx       f32              weight scale        } repeated
+/// xxxx       i32              weight zero point   } for every
+/// xxxx       f32              input scale         } layer
+/// xxxx       i32              input zero point    }
+/// xxxx       i8               weight, row-major   }
+/// ...                                             }
+/// xxxx       i32              bias                }
+/// ...
+const QUANT_MAGIC: &[u8; 4] = b"NETQ";
+const QUANT_VERSION: u32 = 1;
+
+/// Affine mapping between reals and int8, real = scale * ( q - zero )
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuantParams {
+    pub scale: f32,
+    pub zero: i32
+}
+
+impl QuantParams {
+    /// Maps [min, max], widened to hold zero exactly, onto the int8 range
+    pub fn from_range(min: f32, max: f32) -> Self {
+        let (min, max) = (min.min(0.), max.max(0.));
+        let scale = if max > min { (max - min) / 255. } else { 1. };
+        let zero = (-128. - min / scale).round().clamp(-128., 127.) as i32;
+
+        Self { scale, zero }
+    }
+
+    pub fn quantize(&self, x: f32) -> i8 {
+        ((x / self.scale).round() as i32 + self.zero).clamp(-128, 127) as i8
+    }
+
+    pub fn dequantize(&self, q: i8) -> f32 {
+        self.scale * (q as i32 - self.zero) as f32
+    }
+}
+
+/// Row-major int8 matrix multiplied with i32 accumulation
+#[derive(Clone)]
+pub struct Int8Matrix {
+    row: usize,
+
+    col: usize,
+
+    buf: Vec<i8>
+}
+
+impl Int8Matrix {
+    pub fn shape(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// Multiplies ( self - zero ) by ( input - x_zero ) into buf,
+    /// accumulating in i32 and failing on unmatched dimensions
+    pub fn try_mul_to<'a>(&self, zero: i32, input: &[i8], x_zero: i32, buf: &'a mut Vector<i32>) -> Result<&'a mut Vector<i32>> {
+        if self.col != input.len() || self.row != buf.row() {
+            return Err(NetError::Dimension { op: "mul", lhs: self.shape(), rhs: (input.len(), 1) })
+        }
+
+        for (r, row) in self.buf.chunks_exact(self.col.max(1)).enumerate() {
+            buf[r] = row
+                .iter()
+                .zip(input)
+                .map(|(w, x)| (*w as i32 - zero) * (*x as i32 - x_zero))
+                .sum();
+        }
+
+        Ok(buf)
+    }
+}
+
+/// Int8 layer with the calibrated mappings of its weights and inputs
+#[derive(Clone)]
+struct QuantLayer {
+    weights: Int8Matrix,
+
+    // biases at the product scale of weights and inputs
+    biases: Vec<i32>,
+
+    weight: QuantParams,
+
+    input: QuantParams
+}
+
+/// Binary format meta data of a quantized model
+#[derive(Serialize, Deserialize)]
+struct Meta {
+    act: Activation,
+
+    #[serde(default)]
+    out: Output
+}
+
+/// Inference-only network with int8 weights and activations, computing
+/// each layer in integers before rescaling for the step function
+#[derive(Clone)]
+pub struct Quantized {
+    // layer sizes
+    form: Vec<usize>,
+
+    layers: Vec<QuantLayer>,
+
+    // step function
+    act: Activation,
+
+    // output layer function
+    out: Output
+}
+
+/// Accuracy of a quantized model relative to its f32 original
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct QuantReport {
+    pub accuracy: f32,
+
+    pub quantized_accuracy: f32,
+
+    // accuracy lost by quantizing
+    pub drop: f32,
+
+    // parameter bytes of the f32 model
+    pub bytes: usize,
+
+    // parameter bytes of the quantized model
+    pub quantized_bytes: usize
+}
+
+impl fmt::Display for QuantReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "f32 accuracy of {} in {} bytes", self.accuracy, self.bytes)?;
+        writeln!(f, "int8 accuracy of {} in {} bytes", self.quantized_accuracy, self.quantized_bytes)?;
+        writeln!(f, "accuracy drop of {}", self.drop)
+    }
+}
+
+impl Quantized {
+    /// Quantizes the net, calibrating the range of every layer's
+    /// inputs on samples representative of the inference data
+    pub fn calibrate<const L: usize>(net: &Net<L>, samples: &[Vector]) -> Self {
+        Self::try_calibrate(net, samples)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_calibrate<const L: usize>(net: &Net<L>, samples: &[Vector]) -> Result<Self> {
+        if samples.is_empty() {
+            return Err(NetError::EmptyCalibration)
+        }
+
+        let form = &net.data.form;
+
+        // observed ( min, max ) of every layer's inputs
+        let mut ranges = vec![(0_f32, 0_f32); L-1];
+
+        for sample in samples {
+            if sample.shape() != (form[0], 1) {
+                return Err(NetError::Shape { expected: (form[0], 1), found: sample.shape() })
+            }
+
+            let mut act = sample.clone();
+
+            for l in 0..L-1 {
+                let range = &mut ranges[l];
+
+                for n in act.buf() {
+                    *range = (range.0.min(*n), range.1.max(*n));
+                }
+
+                let mut sum = Vector::from_zeros(form[l+1]);
+                net.weights[l].mul_to(&act, &mut sum);
+                sum.add_eq(&net.biases[l]);
+                net.data.activate_eq(l, &mut sum);
+
+                act = sum;
+            }
+        }
+
+        let layers = (0..L-1)
+            .map(|l| {
+                let weights = net.weights[l].buf();
+
+                let min = weights.iter().fold(f32::MAX, |min, n| min.min(*n));
+                let max = weights.iter().fold(f32::MIN, |max, n| max.max(*n));
+
+                let weight = QuantParams::from_range(min, max);
+                let input = QuantParams::from_range(ranges[l].0, ranges[l].1);
+
+                let bias_scale = weight.scale * input.scale;
+
+                QuantLayer {
+                    weights: Int8Matrix {
+                        row: form[l+1],
+                        col: form[l],
+                        buf: weights.iter().map(|n| weight.quantize(*n)).collect()
+                    },
+                    biases: net.biases[l].buf().iter().map(|n| (n / bias_scale).round() as i32).collect(),
+                    weight,
+                    input
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            form: form.clone(),
+            layers,
+            act: net.data.act,
+            out: net.data.out
+        })
+    }
+
+    pub fn form(&self) -> &[usize] {
+        &self.form
+    }
+
+    pub fn act(&self) -> Activation {
+        self.act
+    }
+
+    pub fn out(&self) -> Output {
+        self.out
+    }
+
+    pub fn forward_prop(&self, input: &Vector) -> Vector {
+        match self.try_forward_prop(input) {
+            Ok(out) => out,
+            Err(err) => panic!("{}", err)
+        }
+    }
+
+    pub fn try_forward_prop(&self, input: &Vector) -> Result<Vector> {
+        if input.shape() != (self.form[0], 1) {
+            return Err(NetError::Shape { expected: (self.form[0], 1), found: input.shape() })
+        }
+
+        let mut act = input.clone();
+
+        for (l, layer) in self.layers.iter().enumerate() {
+            let input: Vec<i8> = act.buf().iter().map(|n| layer.input.quantize(*n)).collect();
+
+            let mut acc = Vector::from_zeros(layer.weights.row);
+            layer.weights.try_mul_to(layer.weight.zero, &input, layer.input.zero, &mut acc)?;
+
+            let scale = layer.weight.scale * layer.input.scale;
+
+            let mut sum = Vector::from_buf(layer.weights.row, acc
+                .buf()
+                .iter()
+                .zip(&layer.biases)
+                .map(|(n, b)| scale * n.saturating_add(*b) as f32)
+                .collect());
+
+            if l == self.layers.len()-1 {
+                self.out.value_eq(self.act, &mut sum);
+            }
+            else {
+                sum.map_eq(|n| self.act.value(n));
+            }
+
+            act = sum;
+        }
+
+        Ok(act)
+    }
+
+    /// Compares accuracy and parameter size against the f32 net
+    pub fn report<const L: usize>(&self, net: &Net<L>, inputs: &[Vector], outs: &[Vector]) -> QuantReport {
+        let accuracy = net.accuracy(inputs, outs);
+        let quantized_accuracy = self.accuracy(inputs, outs);
+
+        let params: usize = (1..self.form.len()).map(|l| self.form[l] * self.form[l-1] + self.form[l]).sum();
+        let quantized_bytes = self.layers
+            .iter()
+            .map(|layer| layer.weights.buf.len() + 4 * layer.biases.len() + 16)
+            .sum();
+
+        QuantReport {
+            accuracy,
+            quantized_accuracy,
+            drop: accuracy - quantized_accuracy,
+            bytes: 4 * params,
+            quantized_bytes
+        }
+    }
+
+    /// Atomically writes the model to path in the quantized format
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::write_atomic(path, &self.to_bytes()?)
+    }
+
+    /// Writes the model to writer in the quantized format
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Self {
+        Self::try_from_file(path)
+            .unwrap_or_else(|err| panic!("couldn't load quantized model: {}", err))
+    }
+
+    pub fn try_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_from(format::open(path)?)
+    }
+
+    pub fn read_from<R: Read>(reader: R) -> Result<Self> {
+        let bytes = format::read_all(reader)?;
+
+        Self::from_bytes(&bytes)
+    }
+
+    fn to_bytes(&self) -> Result<Vec<u8>> {
+        let meta = serde_json::to_vec(&Meta { act: self.act, out: self.out })?;
+        let mut bytes = Vec::new();
+
+        bytes.extend_from_slice(QUANT_MAGIC);
+        bytes.extend_from_slice(&QUANT_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.form.len() as u32).to_le_bytes());
+
+        for size in self.form.iter() {
+            bytes.extend_from_slice(&(*size as u32).to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&(meta.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&meta);
+
+        for layer in self.layers.iter() {
+            bytes.extend_from_slice(&layer.weight.scale.to_le_bytes());
+            bytes.extend_from_slice(&layer.weight.zero.to_le_bytes());
+            bytes.extend_from_slice(&layer.input.scale.to_le_bytes());
+            bytes.extend_from_slice(&layer.input.zero.to_le_bytes());
+            bytes.extend(layer.weights.buf.iter().map(|n| *n as u8));
+
+            for n in layer.biases.iter() {
+                bytes.extend_from_slice(&n.to_le_bytes());
+            }
+        }
+
+        Ok(bytes)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = ByteReader::new(bytes);
+
+        if reader.take(4)? != QUANT_MAGIC {
+            return Err(NetError::Corrupt("missing quantized magic number"))
+        }
+
+        let version = reader.u32()?;
+        if version != QUANT_VERSION {
+            return Err(NetError::Version { found: version, supported: QUANT_VERSION })
+        }
+
+        let layers = reader.u32()? as usize;
+        if layers <= 2 {
+            return Err(NetError::TooFewLayers(layers))
+        }
+
+        let form = (0..layers)
+            .map(|_| reader.u32().map(|n| n as usize))
+            .collect::<Result<Vec<_>>>()?;
+
+        let meta_len = reader.u32()? as usize;
+        let meta: Meta = serde_json::from_slice(reader.take(meta_len)?)?;
+
+        let layers = (1..layers)
+            .map(|l| {
+                let weight = QuantParams { scale: reader.f32()?, zero: reader.u32()? as i32 };
+                let input = QuantParams { scale: reader.f32()?, zero: reader.u32()? as i32 };
+
+                let size = form[l]
+                    .checked_mul(form[l-1])
+                    .ok_or(NetError::Corrupt("layer too large"))?;
+
+                Ok(QuantLayer {
+                    weights: Int8Matrix { row: form[l], col: form[l-1], buf: reader.i8s(size)? },
+                    biases: reader.i32s(form[l])?,
+                    weight,
+                    input
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        if !reader.is_done() {
+            return Err(NetError::Corrupt("trailing bytes after parameters"))
+        }
+
+        Ok(Self { form, layers, act: meta.act, out: meta.out })
+    }
+}
+
+impl Classifier for Quantized {
+    fn try_output(&self, input: &Vector) -> Result<Vector> {
+        self.try_forward_prop(input)
+    }
+
+    fn normalize_output(&self, out: &Vector) -> Vector {
+        to_probabilities(out, self.act, self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn int8_product_accumulates_offset_values() {
+        let weights = Int8Matrix { row: 2, col: 3, buf: vec![1, -2, 3, 127, -128, 0] };
+        let mut buf = Vector::from_zeros(2);
+
+        weights.try_mul_to(1, &[4, 5, -6], -1, &mut buf).unwrap();
+
+        // ( w - 1 ) * ( x + 1 ), i.e. [ 0, -3, 2 ] and [ 126, -129, -1 ] by [ 5, 6, -5 ]
+        assert_eq!(buf.buf(), &vec![-28, -139]);
+    }
+
+    #[test]
+    fn int8_product_fails_on_unmatched_dimensions() {
+        let weights = Int8Matrix { row: 2, col: 3, buf: vec![0; 6] };
+
+        assert!(matches!(weights.try_mul_to(0, &[1, 2], 0, &mut Vector::from_zeros(2)), Err(NetError::Dimension { .. })));
+        assert!(matches!(weights.try_mul_to(0, &[1, 2, 3], 0, &mut Vector::from_zeros(3)), Err(NetError::Dimension { .. })));
+    }
+}
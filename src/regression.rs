@@ -0,0 +1,216 @@
+use std::fmt;
+use std::path::Path;
+
+use serde_derive::{Serialize, Deserialize};
+
+use crate::error::{NetError, Result};
+use crate::format;
+use crate::linalg::*;
+use crate::model::Model;
+use crate::net::Net;
+
+/// Error measures of a single output dimension or of all of them
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct RegressionScores {
+    // mean squared error
+    pub mse: f32,
+
+    // mean absolute error
+    pub mae: f32,
+
+    // root mean squared error
+    pub rmse: f32,
+
+    // coefficient of determination
+    pub r2: f32
+}
+
+/// Regression report of a network over a data set
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Regression {
+    // evaluated samples
+    pub samples: usize,
+
+    // scores of each output dimension
+    pub dims: Vec<RegressionScores>,
+
+    // errors over all outputs, with the mean R² of the dimensions
+    pub total: RegressionScores
+}
+
+/// Running per-dimension sums from which regression scores are computed
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RegressionSums {
+    samples: usize,
+
+    // sums of targets
+    y: Vec<f64>,
+
+    // sums of squared targets
+    y2: Vec<f64>,
+
+    // sums of squared errors
+    se: Vec<f64>,
+
+    // sums of absolute errors
+    ae: Vec<f64>
+}
+
+impl RegressionSums {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an output and its target, failing unless both are of the
+    /// shape of the first target added
+    pub(crate) fn add(&mut self, out: &Vector, target: &Vector) -> Result<()> {
+        if self.samples == 0 {
+            let dims = target.row();
+            *self = Self { samples: 0, y: vec![0.; dims], y2: vec![0.; dims], se: vec![0.; dims], ae: vec![0.; dims] };
+        }
+
+        let shape = (self.y.len(), 1);
+
+        for found in [target.shape(), out.shape()] {
+            if found != shape {
+                return Err(NetError::Shape { expected: shape, found })
+            }
+        }
+
+        for (d, (o, t)) in out.buf().iter().zip(target.buf()).enumerate() {
+            let (o, t) = (*o as f64, *t as f64);
+
+            self.y[d] += t;
+            self.y2[d] += t * t;
+            self.se[d] += (t - o).powi(2);
+            self.ae[d] += (t - o).abs();
+        }
+
+        self.samples += 1;
+
+        Ok(())
+    }
+
+    pub(crate) fn report(&self) -> Regression {
+        let n = self.samples.max(1) as f64;
+
+        let dims: Vec<RegressionScores> = (0..self.y.len())
+            .map(|d| {
+                // total sum of squares around the target mean
+                let ss = self.y2[d] - self.y[d].powi(2) / n;
+                scores(self.se[d] / n, self.ae[d] / n, r2(self.se[d], ss))
+            })
+            .collect();
+
+        let outputs = n * self.y.len().max(1) as f64;
+        let r2 = dims.iter().map(|s| s.r2 as f64).sum::<f64>() / dims.len().max(1) as f64;
+
+        let total = scores(
+            self.se.iter().sum::<f64>() / outputs,
+            self.ae.iter().sum::<f64>() / outputs,
+            r2
+        );
+
+        Regression { samples: self.samples, dims, total }
+    }
+}
+
+impl Regression {
+    /// Builds the report from network outputs and their targets
+    pub fn from_outputs(outs: &[Vector], targets: &[Vector]) -> Self {
+        Self::try_from_outputs(outs, targets)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_from_outputs(outs: &[Vector], targets: &[Vector]) -> Result<Self> {
+        if outs.len() != targets.len() {
+            return Err(NetError::Length { inputs: outs.len(), targets: targets.len() })
+        }
+
+        let mut sums = RegressionSums::new();
+
+        for (out, target) in outs.iter().zip(targets) {
+            sums.add(out, target)?;
+        }
+
+        Ok(sums.report())
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        format::to_json(self)
+    }
+
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_json(self, path)
+    }
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>9} {:>9} {:>9} {:>9} {:>9}", "output", "mse", "mae", "rmse", "r2")?;
+
+        for (d, s) in self.dims.iter().enumerate() {
+            writeln!(f, "{:>9} {:>9.4} {:>9.4} {:>9.4} {:>9.4}", d, s.mse, s.mae, s.rmse, s.r2)?;
+        }
+
+        let s = self.total;
+        writeln!(f, "\n{:>9} {:>9.4} {:>9.4} {:>9.4} {:>9.4}", "total", s.mse, s.mae, s.rmse, s.r2)
+    }
+}
+
+impl<const L: usize> Net<L> {
+    /// Returns the regression report over a data set
+    pub fn regression(&self, inputs: &[Vector], targets: &[Vector]) -> Regression {
+        self.try_regression(inputs, targets)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_regression(&self, inputs: &[Vector], targets: &[Vector]) -> Result<Regression> {
+        if inputs.len() != targets.len() {
+            return Err(NetError::Length { inputs: inputs.len(), targets: targets.len() })
+        }
+
+        let outs = inputs
+            .iter()
+            .map(|input| self.try_predict(input))
+            .collect::<Result<Vec<_>>>()?;
+
+        Regression::try_from_outputs(&outs, targets)
+    }
+}
+
+impl Model {
+    /// Returns the regression report over a data set
+    pub fn regression(&self, inputs: &[Vector], targets: &[Vector]) -> Regression {
+        self.try_regression(inputs, targets)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_regression(&self, inputs: &[Vector], targets: &[Vector]) -> Result<Regression> {
+        if inputs.len() != targets.len() {
+            return Err(NetError::Length { inputs: inputs.len(), targets: targets.len() })
+        }
+
+        let outs = inputs
+            .iter()
+            .map(|input| self.try_forward_prop(input))
+            .collect::<Result<Vec<_>>>()?;
+
+        Regression::try_from_outputs(&outs, targets)
+    }
+}
+
+fn scores(mse: f64, mae: f64, r2: f64) -> RegressionScores {
+    RegressionScores { mse: mse as f32, mae: mae as f32, rmse: mse.sqrt() as f32, r2: r2 as f32 }
+}
+
+/// Returns 1 - SSE / SST, which is undefined for constant targets
+/// and then only 1 for a perfect fit
+fn r2(sse: f64, sst: f64) -> f64 {
+    if sst <= f64::EPSILON {
+        if sse <= f64::EPSILON { 1. } else { 0. }
+    }
+    else {
+        1. - sse / sst
+    }
+}
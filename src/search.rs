@@ -0,0 +1,428 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use rand::Rng;
+use serde_derive::Serialize;
+
+use crate::error::{NetError, Result};
+use crate::format;
+use crate::linalg::*;
+use crate::net::{HyperData, Net};
+use crate::step::Activation;
+
+/// Searched values of a numeric hyper parameter
+#[derive(Clone, Debug)]
+pub enum Param {
+    // fixed set of values
+    Choice(Vec<f32>),
+
+    // evenly spaced steps between min and max, or any value between them when sampled
+    Range { min: f32, max: f32, steps: usize },
+
+    // steps evenly spaced in log scale between min and max, suited to learn rates
+    LogRange { min: f32, max: f32, steps: usize }
+}
+
+impl Param {
+    /// Returns the values of a grid search
+    fn values(&self) -> Vec<f32> {
+        let spaced = |min: f32, max: f32, steps: usize| -> Vec<f32> {
+            match steps {
+                0 => Vec::new(),
+                1 => vec![min],
+                _ => (0..steps).map(|i| min + (max - min) * i as f32 / (steps-1) as f32).collect()
+            }
+        };
+
+        match self {
+            Param::Choice(values) => values.clone(),
+            Param::Range { min, max, steps } => spaced(*min, *max, *steps),
+            Param::LogRange { min, max, steps } => spaced(min.ln(), max.ln(), *steps)
+                .into_iter()
+                .map(f32::exp)
+                .collect()
+        }
+    }
+
+    /// Returns a random value of a random search
+    fn sample<R: Rng>(&self, rng: &mut R) -> f32 {
+        match self {
+            Param::Choice(values) => values[rng.gen_range(0..values.len())],
+            Param::Range { min, max, .. } => rng.gen_range(*min..=*max),
+            Param::LogRange { min, max, .. } => rng.gen_range(min.ln()..=max.ln()).exp()
+        }
+    }
+
+    /// Returns whether the parameter has values to search, in log scale only positive ones
+    fn is_valid(&self) -> bool {
+        match self {
+            Param::Choice(values) => !values.is_empty(),
+            Param::Range { min, max, steps } => *steps > 0 && min <= max,
+            Param::LogRange { min, max, steps } => *steps > 0 && *min > 0. && min <= max
+        }
+    }
+}
+
+/// Enumerated way of choosing the candidates
+#[derive(Clone, Copy, Debug)]
+pub enum Strategy {
+    // every combination of the searched values
+    Grid,
+
+    // given number of combinations drawn at random
+    Random(usize)
+}
+
+/// Hyper parameters of a single search candidate
+#[derive(Clone)]
+struct Candidate<const L: usize> {
+    learn_rate: f32,
+
+    batch_size: usize,
+
+    act: Activation,
+
+    form: [usize; L]
+}
+
+/// Search driver training a net for every candidate set of hyper
+/// parameters and ranking them by validation loss
+pub struct Search<const L: usize> {
+    // hyper parameters shared by every candidate
+    base: HyperData<L>,
+
+    learn_rate: Option<Param>,
+
+    batch_size: Option<Param>,
+
+    acts: Vec<Activation>,
+
+    forms: Vec<[usize; L]>,
+
+    strategy: Strategy,
+
+    // training epochs of every candidate
+    epochs: usize,
+
+    // controls training the candidates on all available threads
+    parallel: bool,
+
+    // directory receiving the leaderboard and best model
+    dir: Option<String>
+}
+
+impl<const L: usize> Search<L> {
+    pub fn new(base: &HyperData<L>, strategy: Strategy, epochs: usize) -> Self {
+        Self {
+            base: base.clone(),
+            learn_rate: None,
+            batch_size: None,
+            acts: Vec::new(),
+            forms: Vec::new(),
+            strategy,
+            epochs,
+            parallel: false,
+            dir: None
+        }
+    }
+
+    pub fn with_learn_rate(&mut self, param: Param) -> &mut Self {
+        self.learn_rate = Some(param);
+        self
+    }
+
+    /// Searches batch sizes, rounding the values to whole samples
+    pub fn with_batch_size(&mut self, param: Param) -> &mut Self {
+        self.batch_size = Some(param);
+        self
+    }
+
+    pub fn with_acts(&mut self, acts: &[Activation]) -> &mut Self {
+        self.acts = acts.to_vec();
+        self
+    }
+
+    /// Searches layer forms, which must share their input and output sizes
+    pub fn with_forms(&mut self, forms: &[[usize; L]]) -> &mut Self {
+        self.forms = forms.to_vec();
+        self
+    }
+
+    pub fn with_parallel(&mut self, state: bool) -> &mut Self {
+        self.parallel = state;
+        self
+    }
+
+    /// Writes leaderboard.json and best.json to dir after searching
+    pub fn with_dir(&mut self, dir: &str) -> &mut Self {
+        self.dir = Some(dir.to_string());
+        self
+    }
+
+    /// Returns the number of candidates trained by the search
+    pub fn len(&self) -> usize {
+        match self.strategy {
+            Strategy::Grid => self.learn_rates().len() * self.batch_sizes().len() * self.act_choices().len() * self.form_choices().len(),
+            Strategy::Random(n) => n
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn run(&self, inputs: &[Vector], targets: &[Vector], valid_inputs: &[Vector], valid_targets: &[Vector]) -> Leaderboard<L> {
+        self.try_run(inputs, targets, valid_inputs, valid_targets)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Trains and validates every candidate, failing on malformed data
+    /// or if the leaderboard couldn't be written
+    pub fn try_run(&self, inputs: &[Vector], targets: &[Vector], valid_inputs: &[Vector], valid_targets: &[Vector]) -> Result<Leaderboard<L>> {
+        if [&self.learn_rate, &self.batch_size].into_iter().flatten().any(|p| !p.is_valid()) {
+            return Err(NetError::Search("searched parameter without valid values"))
+        }
+
+        let candidates = self.candidates();
+
+        if candidates.is_empty() {
+            return Err(NetError::Search("no candidates to search"))
+        }
+
+        for candidate in candidates.iter() {
+            self.to_data(candidate).check_training(inputs, targets, Some((valid_inputs, valid_targets)))?;
+        }
+
+        let train = |candidate: &Candidate<L>| {
+            let mut net = self.to_data(candidate).build();
+            let history = net.train_with(inputs, targets, Some((valid_inputs, valid_targets)), self.epochs, &mut []);
+
+            let trial = Trial {
+                learn_rate: candidate.learn_rate,
+                batch_size: candidate.batch_size,
+                act: candidate.act,
+                form: candidate.form.to_vec(),
+                epochs: history.len(),
+                loss: net.loss(valid_inputs, valid_targets),
+                accuracy: net.accuracy(valid_inputs, valid_targets),
+                time: history.time()
+            };
+
+            (trial, net)
+        };
+
+        // scores of every trial, but only the best net so far
+        let results = Mutex::new((Vec::with_capacity(candidates.len()), None));
+
+        let record = |(trial, net): (Trial, Net<L>)| {
+            let (trials, best): &mut (Vec<Trial>, Option<(f32, Net<L>)>) = &mut results.lock().unwrap();
+
+            if best.as_ref().is_none_or(|(loss, _)| trial.loss.total_cmp(loss).is_lt()) {
+                *best = Some((trial.loss, net));
+            }
+
+            trials.push(trial);
+        };
+
+        if self.parallel {
+            let workers = thread::available_parallelism().map_or(1, |n| n.get()).min(candidates.len());
+            let next = AtomicUsize::new(0);
+
+            thread::scope(|scope| {
+                for _ in 0..workers {
+                    scope.spawn(|| {
+                        while let Some(candidate) = candidates.get(next.fetch_add(1, Ordering::Relaxed)) {
+                            record(train(candidate));
+                        }
+                    });
+                }
+            });
+        }
+        else {
+            candidates.iter().map(train).for_each(record);
+        }
+
+        let (mut trials, best) = results.into_inner().unwrap();
+
+        // stable, so the best net stays the first trial of equal loss
+        trials.sort_by(|a, b| a.loss.total_cmp(&b.loss));
+
+        let leaderboard = Leaderboard {
+            trials,
+            best: best.expect("search without trials").1
+        };
+
+        if let Some(dir) = &self.dir {
+            std::fs::create_dir_all(dir)?;
+            leaderboard.save_json(Path::new(dir).join("leaderboard.json"))?;
+            leaderboard.best.save_to(Path::new(dir).join("best.json"))?;
+        }
+
+        Ok(leaderboard)
+    }
+
+    /// Returns the candidates of the search strategy
+    fn candidates(&self) -> Vec<Candidate<L>> {
+        match self.strategy {
+            Strategy::Grid => {
+                let mut candidates = Vec::with_capacity(self.len());
+
+                for learn_rate in self.learn_rates() {
+                    for batch_size in self.batch_sizes() {
+                        for act in self.act_choices() {
+                            for form in self.form_choices() {
+                                candidates.push(Candidate { learn_rate, batch_size, act, form });
+                            }
+                        }
+                    }
+                }
+
+                candidates
+            }
+            Strategy::Random(n) => {
+                let mut rng = rand::thread_rng();
+                let (acts, forms) = (self.act_choices(), self.form_choices());
+
+                (0..n)
+                    .map(|_| Candidate {
+                        learn_rate: self.learn_rate.as_ref().map_or(self.base.learn_rate, |p| p.sample(&mut rng)),
+                        batch_size: self.batch_size.as_ref().map_or(self.base.batch_size, |p| to_batch_size(p.sample(&mut rng))),
+                        act: acts[rng.gen_range(0..acts.len())],
+                        form: forms[rng.gen_range(0..forms.len())]
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn learn_rates(&self) -> Vec<f32> {
+        self.learn_rate.as_ref().map_or(vec![self.base.learn_rate], Param::values)
+    }
+
+    fn batch_sizes(&self) -> Vec<usize> {
+        self.batch_size.as_ref().map_or(vec![self.base.batch_size], |p| {
+            let mut sizes: Vec<usize> = p.values().into_iter().map(to_batch_size).collect();
+            sizes.sort_unstable();
+            sizes.dedup();
+            sizes
+        })
+    }
+
+    fn act_choices(&self) -> Vec<Activation> {
+        if self.acts.is_empty() { vec![self.base.act] } else { self.acts.clone() }
+    }
+
+    fn form_choices(&self) -> Vec<[usize; L]> {
+        if self.forms.is_empty() {
+            let mut form = [0; L];
+            form.copy_from_slice(&self.base.form);
+            vec![form]
+        }
+        else {
+            self.forms.clone()
+        }
+    }
+
+    /// Returns the hyper parameters of a candidate, whose trials never
+    /// save themselves since concurrent trials would share their files
+    fn to_data(&self, candidate: &Candidate<L>) -> HyperData<L> {
+        let mut data = self.base.clone();
+
+        data.form = candidate.form.to_vec();
+        data.with_learn_rate(candidate.learn_rate)
+            .with_batch_size(candidate.batch_size)
+            .with_act(candidate.act)
+            .with_save_best(false)
+            .with_dir("");
+
+        data
+    }
+}
+
+fn to_batch_size(value: f32) -> usize {
+    value.round().max(1.) as usize
+}
+
+/// Hyper parameters and validation scores of a trained candidate
+#[derive(Clone, Serialize)]
+pub struct Trial {
+    pub learn_rate: f32,
+
+    pub batch_size: usize,
+
+    pub act: Activation,
+
+    pub form: Vec<usize>,
+
+    // epochs trained, fewer than searched if stopped early
+    pub epochs: usize,
+
+    // mean validation cost
+    pub loss: f32,
+
+    // validation accuracy
+    pub accuracy: f32,
+
+    // training time in seconds
+    pub time: f32
+}
+
+/// Candidates ranked by validation loss along with the best trained net
+#[derive(Serialize)]
+pub struct Leaderboard<const L: usize> {
+    trials: Vec<Trial>,
+
+    #[serde(skip)]
+    best: Net<L>
+}
+
+impl<const L: usize> Leaderboard<L> {
+    /// Returns the trials from lowest to highest validation loss
+    pub fn trials(&self) -> &[Trial] {
+        &self.trials
+    }
+
+    pub fn best_trial(&self) -> &Trial {
+        &self.trials[0]
+    }
+
+    pub fn best(&self) -> &Net<L> {
+        &self.best
+    }
+
+    pub fn into_best(self) -> Net<L> {
+        self.best
+    }
+
+    /// Atomically writes the best net to path
+    pub fn save_best<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.best.save_to(path)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        format::to_json(self)
+    }
+
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_json(self, path)
+    }
+}
+
+impl<const L: usize> fmt::Display for Leaderboard<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}  form", "rank", "loss", "accuracy", "rate", "batch", "act", "epochs")?;
+
+        for (rank, t) in self.trials.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>9} {:>9.4} {:>9.3} {:>9.5} {:>9} {:>9} {:>9}  {:?}",
+                rank+1, t.loss, t.accuracy, t.learn_rate, t.batch_size, format!("{:?}", t.act), t.epochs, t.form
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,8 +1,10 @@
 use std::f32::consts::E;
 use serde_derive::{Serialize, Deserialize};
 
+use crate::linalg::{LinAlgGen, Vector};
+
 /// Enumerated network activation function
-#[derive(Clone, Copy, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum Activation {
     Sig,
     Tanh,
@@ -26,4 +28,68 @@ impl Activation {
             Activation::Lin =>  1.
         }
     }
-}
\ No newline at end of file
+}
+
+/// Enumerated network output layer function
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Output {
+    // network activation function applied element-wise
+    #[default]
+    Act,
+
+    // normalized exponentials summing to one
+    Softmax,
+
+    // independent logistic probabilities
+    Sigmoid
+}
+
+impl Output {
+    pub fn value(&self, act: Activation, sums: &Vector) -> Vector {
+        match self {
+            Output::Act => sums.map(|n| act.value(n)),
+            Output::Softmax => softmax(sums),
+            Output::Sigmoid => sums.map(|n| Activation::Sig.value(n))
+        }
+    }
+
+    pub fn value_eq(&self, act: Activation, sums: &mut Vector) {
+        match self {
+            Output::Act => {
+                sums.map_eq(|n| act.value(n));
+            }
+            Output::Softmax => {
+                *sums = softmax(sums);
+            }
+            Output::Sigmoid => {
+                sums.map_eq(|n| Activation::Sig.value(n));
+            }
+        }
+    }
+
+    /// Propagates the output error through the layer function
+    pub fn deriv_eq(&self, act: Activation, sums: &Vector, acts: &Vector, err: &mut Vector) {
+        match self {
+            Output::Act => {
+                err.dot_eq(&sums.map(|n| act.deriv(n)));
+            }
+            Output::Softmax => {
+                // error_i = soft_i * ( err_i - sum_j ( err_j * soft_j ) )
+                let dot: f32 = err.dot(acts).buf().iter().sum();
+                err.map_eq(|n| n - dot).dot_eq(acts);
+            }
+            Output::Sigmoid => {
+                err.dot_eq(&acts.map(|a| a * (1. - a)));
+            }
+        }
+    }
+}
+
+/// Returns the normalized exponentials of the vector
+pub fn softmax(vec: &Vector) -> Vector {
+    let max = vec.buf().iter().fold(f32::MIN, |max, n| max.max(*n));
+    let exp = vec.map(|n| (n - max).exp());
+    let sum: f32 = exp.buf().iter().sum();
+
+    exp.scale(1. / sum)
+}
@@ -0,0 +1,291 @@
+use std::fmt;
+use std::mem::size_of;
+use std::path::Path;
+
+use serde_derive::Serialize;
+
+use crate::error::Result;
+use crate::format;
+use crate::linalg::*;
+use crate::model::Model;
+use crate::net::Net;
+use crate::prune::Sparse;
+use crate::quantize::{Quantized, QuantParams};
+use crate::step::{Activation, Output};
+
+/// Shape, function and size of a single weight layer
+#[derive(Clone, Debug, Serialize)]
+pub struct LayerSummary {
+    pub inputs: usize,
+
+    pub outputs: usize,
+
+    pub weights: (usize, usize),
+
+    pub biases: (usize, usize),
+
+    // step function, or output function of the last layer
+    pub act: String,
+
+    // weights and biases, only the stored weights of sparse models
+    pub params: usize,
+
+    // params updated by training, zero if frozen
+    pub trainable: usize,
+
+    // bytes of the params as stored, with their pruning masks or indices
+    pub param_bytes: usize,
+
+    // bytes of the training buffers, zero for inference-only models
+    pub buffer_bytes: usize
+}
+
+/// Per-layer table of a network with its total size
+#[derive(Clone, Debug, Serialize)]
+pub struct Summary {
+    pub layers: Vec<LayerSummary>,
+
+    pub params: usize,
+
+    pub trainable: usize,
+
+    pub param_bytes: usize,
+
+    pub buffer_bytes: usize
+}
+
+impl Summary {
+    /// Summarizes the layers of form, where params returns the params
+    /// with their bytes, buffers the training buffer bytes and trainable
+    /// how many of a layer's params training updates
+    fn new<P, B, T>(form: &[usize], act: Activation, out: Output, params: P, buffers: B, trainable: T) -> Self
+    where
+        P: Fn(usize) -> (usize, usize),
+        B: Fn(usize) -> usize,
+        T: Fn(usize, usize) -> usize
+    {
+        let layers: Vec<LayerSummary> = (0..form.len()-1)
+            .map(|l| {
+                let (inputs, outputs) = (form[l], form[l+1]);
+                let (params, param_bytes) = params(l);
+
+                let act = if l == form.len()-2 && out != Output::Act {
+                    format!("{:?}", out)
+                }
+                else {
+                    format!("{:?}", act)
+                };
+
+                LayerSummary {
+                    inputs,
+                    outputs,
+                    weights: (outputs, inputs),
+                    biases: (outputs, 1),
+                    act,
+                    params,
+                    trainable: trainable(l, params),
+                    param_bytes,
+                    buffer_bytes: buffers(l)
+                }
+            })
+            .collect();
+
+        Self {
+            params: layers.iter().map(|layer| layer.params).sum(),
+            trainable: layers.iter().map(|layer| layer.trainable).sum(),
+            param_bytes: layers.iter().map(|layer| layer.param_bytes).sum(),
+            buffer_bytes: layers.iter().map(|layer| layer.buffer_bytes).sum(),
+            layers
+        }
+    }
+
+    /// Returns the bytes of the params and training buffers
+    pub fn total_bytes(&self) -> usize {
+        self.param_bytes + self.buffer_bytes
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        format::to_json(self)
+    }
+
+    pub fn save_json<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_json(self, path)
+    }
+}
+
+/// Returns the dense params of layer l in form
+fn dense(form: &[usize], l: usize) -> usize {
+    form[l+1] * form[l] + form[l+1]
+}
+
+impl<const L: usize> Net<L> {
+    /// Summarizes the layers, counting the bytes of the activation,
+    /// summation, error and weight error buffers used by training and
+    /// the best validated params kept by early stopping
+    pub fn summary(&self) -> Summary {
+        let form = &self.data.form;
+
+        // pruning masks are saved along with the weights
+        let params = |l: usize| {
+            let params = dense(form, l);
+            let masks = if self.masks.is_some() { form[l+1] * form[l] } else { 0 };
+
+            (params, (params + masks) * size_of::<f32>())
+        };
+
+        let buffers = |l: usize| {
+            let weights = form[l+1] * form[l];
+
+            // activations, summations, errors and error accumulators
+            let mut floats = 4 * form[l+1] + 2 * weights;
+
+            if self.data.patience.is_some() || self.data.save_best {
+                floats += dense(form, l);
+            }
+
+            // the input activations belong to the first layer
+            if l == 0 {
+                floats += form[0];
+            }
+
+            floats * size_of::<f32>()
+        };
+
+        // frozen layers train none of their params and pruned weights stay zero
+        let trainable = |l: usize, params: usize| {
+            let pruned = self.masks
+                .as_ref()
+                .map_or(0, |masks| masks[l].buf().iter().filter(|m| **m == 0.).count());
+
+            if self.is_frozen(l) { 0 } else { params - pruned }
+        };
+
+        Summary::new(form, self.data.act, self.data.out, params, buffers, trainable)
+    }
+}
+
+impl Model {
+    pub fn summary(&self) -> Summary {
+        let params = |l: usize| {
+            let params = dense(self.form(), l);
+            (params, params * size_of::<f32>())
+        };
+
+        Summary::new(self.form(), self.act(), self.out(), params, |_| 0, |_, params| params)
+    }
+}
+
+impl Sparse {
+    /// Summarizes the layers, counting the stored weights with their
+    /// column indices and row pointers
+    pub fn summary(&self) -> Summary {
+        let params = |l: usize| {
+            let weights = &self.weights()[l];
+            let (rows, _) = weights.shape();
+
+            let bytes = weights.nnz() * (size_of::<f32>() + size_of::<u32>())
+                + (rows + 1) * size_of::<u32>()
+                + rows * size_of::<f32>();
+
+            (weights.nnz() + rows, bytes)
+        };
+
+        Summary::new(self.form(), self.act(), self.out(), params, |_| 0, |_, params| params)
+    }
+}
+
+impl Quantized {
+    /// Summarizes the layers, counting int8 weights, i32 biases and
+    /// the weight and input mappings of every layer
+    pub fn summary(&self) -> Summary {
+        let form = self.form();
+
+        let params = |l: usize| {
+            let bytes = form[l+1] * form[l] * size_of::<i8>()
+                + form[l+1] * size_of::<i32>()
+                + 2 * size_of::<QuantParams>();
+
+            (dense(form, l), bytes)
+        };
+
+        Summary::new(form, self.act(), self.out(), params, |_| 0, |_, params| params)
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+            "layer", "inputs", "outputs", "weights", "biases", "act", "params", "trainable", "bytes", "buffers"
+        )?;
+
+        for (l, layer) in self.layers.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+                l+1,
+                layer.inputs,
+                layer.outputs,
+                format!("{}x{}", layer.weights.0, layer.weights.1),
+                format!("{}x{}", layer.biases.0, layer.biases.1),
+                layer.act,
+                layer.params,
+                layer.trainable,
+                layer.param_bytes,
+                layer.buffer_bytes
+            )?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "{:>9} {:>9} {:>9} {:>9}", "", "params", "trainable", "bytes")?;
+        writeln!(f, "{:>9} {:>9} {:>9} {:>9}", "weights", self.params, self.trainable, self.param_bytes)?;
+        writeln!(f, "{:>9} {:>9} {:>9} {:>9}", "buffers", "", "", self.buffer_bytes)?;
+        write!(f, "{:>9} {:>9} {:>9} {:>9} ({})", "total", self.params, self.trainable, self.total_bytes(), to_units(self.total_bytes()))
+    }
+}
+
+/// Returns bytes in the largest binary unit below them
+fn to_units(bytes: usize) -> String {
+    let units = ["B", "KiB", "MiB", "GiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024. && unit < units.len()-1 {
+        size /= 1024.;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", size, units[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prune::Sparsity;
+
+    #[test]
+    fn pruned_weights_are_params_but_not_trainable() {
+        let mut net = Net::new([3, 4, 2]).build();
+        let dense = net.summary();
+
+        net.prune(&Sparsity::PerLayer(vec![0.5]));
+        let pruned = net.summary();
+
+        let zeros: usize = net.masks.as_ref().unwrap().buf.iter().map(|m| m.buf().iter().filter(|n| **n == 0.).count()).sum();
+
+        assert_eq!(pruned.params, dense.params);
+        assert_eq!(pruned.trainable, dense.trainable - zeros);
+        assert_eq!(pruned.param_bytes, dense.param_bytes + (4 * 3 + 2 * 4) * size_of::<f32>());
+        assert_eq!(pruned.buffer_bytes, dense.buffer_bytes);
+    }
+
+    #[test]
+    fn early_stopping_buffers_the_best_params() {
+        let plain = Net::new([3, 4, 2]).build().summary();
+        let patient = Net::new([3, 4, 2]).with_patience(2).build().summary();
+
+        assert_eq!(patient.buffer_bytes - plain.buffer_bytes, plain.params * size_of::<f32>());
+    }
+}
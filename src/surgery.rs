@@ -0,0 +1,314 @@
+use rand::Rng;
+
+use crate::array::Array;
+use crate::error::{NetError, Result};
+use crate::linalg::*;
+use crate::net::{HyperData, Net};
+
+impl<const L: usize> Net<L> {
+    /// Returns the net with a hidden layer inserted at form index at,
+    /// sized like the layer before it and initialized to the identity.
+    /// The output is only preserved under a linear step function, under
+    /// Tanh or Sig the step is applied once more and needs fine-tuning
+    pub fn insert_layer<const M: usize>(&self, at: usize) -> Net<M> {
+        self.try_insert_layer(at)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_insert_layer<const M: usize>(&self, at: usize) -> Result<Net<M>> {
+        if at == 0 || at >= L {
+            return Err(NetError::Surgery("layer inserted before the input or after the output"))
+        }
+
+        let size = self.data.form[at-1];
+
+        let mut form = self.data.form.clone();
+        form.insert(at, size);
+
+        let frozen = self.data.frozen
+            .iter()
+            .map(|&l| if l >= at-1 { l+1 } else { l })
+            .collect();
+
+        let mut weights = self.weights.buf.clone();
+        let mut biases = self.biases.buf.clone();
+
+        // the new layer passes its input on unchanged up to the step function
+        weights.insert(at-1, Matrix::from_map((size, size), |(r, c)| if r == c { 1. } else { 0. }));
+        biases.insert(at-1, Vector::from_zeros(size));
+
+        let masks = self.edited_masks(|masks| masks.insert(at-1, Matrix::from_fill((size, size), 1.)));
+
+        self.rebuild(self.data.reform(form, frozen)?, weights, biases, masks)
+    }
+
+    /// Returns the net without the hidden layer at form index layer, its
+    /// neighbouring weights collapsed into one as if its step were linear
+    pub fn remove_layer<const M: usize>(&self, layer: usize) -> Net<M> {
+        self.try_remove_layer(layer)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_remove_layer<const M: usize>(&self, layer: usize) -> Result<Net<M>> {
+        self.check_hidden(layer)?;
+
+        let mut form = self.data.form.clone();
+        form.remove(layer);
+
+        let frozen = self.data.frozen
+            .iter()
+            .filter(|&&l| l != layer-1 || self.is_frozen(layer))
+            .filter(|&&l| l != layer)
+            .map(|&l| if l > layer { l-1 } else { l })
+            .collect();
+
+        let (w_in, w_out) = (&self.weights[layer-1], &self.weights[layer]);
+
+        // W' = W_out W_in, b' = W_out b_in + b_out
+        let mut merged = Matrix::from_zeros((w_out.row(), w_in.col()));
+        w_out.mul_to(w_in, &mut merged);
+
+        let mut bias = Vector::from_zeros(w_out.row());
+        w_out.mul_to(&self.biases[layer-1], &mut bias);
+        bias.add_eq(&self.biases[layer]);
+
+        let mut weights = self.weights.buf.clone();
+        let mut biases = self.biases.buf.clone();
+
+        let masks = self.edited_masks(|masks| {
+            masks.splice(layer-1..=layer, [Matrix::from_fill(merged.shape(), 1.)]);
+        });
+
+        weights.splice(layer-1..=layer, [merged]);
+        biases.splice(layer-1..=layer, [bias]);
+
+        self.rebuild(self.data.reform(form, frozen)?, weights, biases, masks)
+    }
+
+    /// Grows the hidden layer at form index layer to size neurons by
+    /// copying randomly chosen existing ones, preserving the output
+    pub fn widen(&mut self, layer: usize, size: usize) {
+        self.try_widen(layer, size)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_widen(&mut self, layer: usize, size: usize) -> Result<()> {
+        self.check_hidden(layer)?;
+
+        let old = self.data.form[layer];
+
+        if size < old {
+            return Err(NetError::Surgery("widened layer smaller than before"))
+        }
+
+        let mut rng = rand::thread_rng();
+
+        // source neuron of every neuron of the widened layer
+        let sources: Vec<usize> = (0..size)
+            .map(|j| if j < old { j } else { rng.gen_range(0..old) })
+            .collect();
+
+        // random shares of the outgoing weights of each source neuron summing to one,
+        // which unlike equal shares lets the copies diverge in training
+        let draws: Vec<f32> = (0..size).map(|_| rng.gen_range(0.5..1.5)).collect();
+        let mut totals = vec![0.; old];
+
+        for (source, draw) in sources.iter().zip(&draws) {
+            totals[*source] += draw;
+        }
+
+        let (w_in, w_out) = (&self.weights[layer-1], &self.weights[layer]);
+        let bias = &self.biases[layer-1];
+
+        let widened_in = Matrix::from_map((size, w_in.col()), |(r, c)| w_in[(sources[r], c)]);
+        let widened_bias = Vector::from_map(size, |r| bias[sources[r]]);
+        let widened_out = Matrix::from_map((w_out.row(), size), |(r, c)| {
+            w_out[(r, sources[c])] * draws[c] / totals[sources[c]]
+        });
+
+        // the copies inherit the masks of their source neurons
+        let masks = self.masks.as_ref().map(|masks| {
+            let (m_in, m_out) = (&masks[layer-1], &masks[layer]);
+
+            (
+                Matrix::from_map(widened_in.shape(), |(r, c)| m_in[(sources[r], c)]),
+                Matrix::from_map(widened_out.shape(), |(r, c)| m_out[(r, sources[c])])
+            )
+        });
+
+        self.resize(layer, size, widened_in, widened_bias, widened_out, masks)
+    }
+
+    /// Removes the given neurons of the hidden layer at form index layer
+    pub fn remove_neurons(&mut self, layer: usize, neurons: &[usize]) {
+        self.try_remove_neurons(layer, neurons)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_remove_neurons(&mut self, layer: usize, neurons: &[usize]) -> Result<()> {
+        self.check_hidden(layer)?;
+
+        if neurons.iter().any(|&n| n >= self.data.form[layer]) {
+            return Err(NetError::Surgery("removed neuron out of range"))
+        }
+
+        let kept: Vec<usize> = (0..self.data.form[layer])
+            .filter(|n| !neurons.contains(n))
+            .collect();
+
+        if kept.is_empty() {
+            return Err(NetError::Surgery("every neuron of the layer removed"))
+        }
+
+        let (w_in, w_out) = (&self.weights[layer-1], &self.weights[layer]);
+        let bias = &self.biases[layer-1];
+
+        let kept_in = Matrix::from_map((kept.len(), w_in.col()), |(r, c)| w_in[(kept[r], c)]);
+        let kept_bias = Vector::from_map(kept.len(), |r| bias[kept[r]]);
+        let kept_out = Matrix::from_map((w_out.row(), kept.len()), |(r, c)| w_out[(r, kept[c])]);
+
+        let masks = self.masks.as_ref().map(|masks| {
+            let (m_in, m_out) = (&masks[layer-1], &masks[layer]);
+
+            (
+                Matrix::from_map(kept_in.shape(), |(r, c)| m_in[(kept[r], c)]),
+                Matrix::from_map(kept_out.shape(), |(r, c)| m_out[(r, kept[c])])
+            )
+        });
+
+        self.resize(layer, kept.len(), kept_in, kept_bias, kept_out, masks)
+    }
+
+    /// Replaces the weights and masks around the hidden layer at form index
+    /// layer, now of size neurons, and rebuilds the training buffers
+    fn resize(
+        &mut self,
+        layer: usize,
+        size: usize,
+        w_in: Matrix,
+        bias: Vector,
+        w_out: Matrix,
+        masks: Option<(Matrix, Matrix)>
+    ) -> Result<()> {
+        let mut form = self.data.form.clone();
+        form[layer] = size;
+
+        let mut weights = self.weights.buf.clone();
+        let mut biases = self.biases.buf.clone();
+
+        weights[layer-1] = w_in;
+        weights[layer] = w_out;
+        biases[layer-1] = bias;
+
+        let masks = self.edited_masks(|old| {
+            if let Some((m_in, m_out)) = masks {
+                old[layer-1] = m_in;
+                old[layer] = m_out;
+            }
+        });
+
+        *self = self.rebuild(self.data.reform(form, self.data.frozen.clone())?, weights, biases, masks)?;
+
+        Ok(())
+    }
+
+    /// Returns the masks of a pruned net after edit, which replaces the
+    /// masks of changed layers and keeps those of untouched ones
+    fn edited_masks<F>(&self, edit: F) -> Option<Vec<Matrix>>
+    where
+        F: FnOnce(&mut Vec<Matrix>)
+    {
+        self.masks.as_ref().map(|masks| {
+            let mut masks = masks.buf.clone();
+            edit(&mut masks);
+            masks
+        })
+    }
+
+    /// Returns a net of the given parameters and masks with fresh training buffers
+    fn rebuild<const M: usize>(
+        &self,
+        data: HyperData<M>,
+        weights: Vec<Matrix>,
+        biases: Vec<Vector>,
+        masks: Option<Vec<Matrix>>
+    ) -> Result<Net<M>> {
+        let mut net = Net::try_from_parts(data)?;
+
+        net.masks = masks.map(Array::from_buf);
+
+        net.weights = Array::from_buf(weights);
+        net.biases = Array::from_buf(biases);
+
+        Ok(net)
+    }
+
+    /// Fails if form index layer isn't a hidden layer
+    fn check_hidden(&self, layer: usize) -> Result<()> {
+        if layer == 0 || layer >= L-1 {
+            return Err(NetError::Surgery("layer is not a hidden layer"))
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prune::Sparsity;
+    use crate::step::Activation;
+
+    const TOLERANCE: f32 = 1e-4;
+
+    fn inputs() -> Vec<Vector> {
+        (0..8).map(|i| Vector::from_arr([(i as f32).sin(), (i as f32 * 0.5).cos(), 0.25])).collect()
+    }
+
+    /// Returns the largest output difference of two nets over the inputs
+    fn max_diff<const L: usize, const M: usize>(a: &mut Net<L>, b: &mut Net<M>) -> f32 {
+        inputs()
+            .iter()
+            .flat_map(|input| {
+                let (out_a, out_b) = (a.forward_prop(input).clone(), b.forward_prop(input).clone());
+                out_a.buf().iter().zip(out_b.buf()).map(|(x, y)| (x - y).abs()).collect::<Vec<_>>()
+            })
+            .fold(0., f32::max)
+    }
+
+    #[test]
+    fn widen_preserves_the_output() {
+        let mut net = Net::new([3, 4, 5, 2]).build();
+        let mut widened = net.clone();
+        widened.widen(1, 7);
+        widened.widen(2, 9);
+
+        assert_eq!(widened.stats().form, vec![3, 7, 9, 2]);
+        assert!(max_diff(&mut net, &mut widened) < TOLERANCE);
+    }
+
+    #[test]
+    fn remove_layer_preserves_a_linear_output() {
+        let mut net = Net::new([3, 4, 5, 2]).with_act(Activation::Lin).build();
+        let mut removed: Net<3> = net.remove_layer(2);
+
+        assert_eq!(removed.stats().form, vec![3, 4, 2]);
+        assert!(max_diff(&mut net, &mut removed) < TOLERANCE);
+    }
+
+    #[test]
+    fn widen_keeps_pruned_weights_pruned() {
+        let inputs = inputs();
+        let targets: Vec<Vector> = (0..8).map(|i| Vector::one_hot(2, i % 2)).collect();
+
+        let mut net = Net::new([3, 4, 2]).with_learn_rate(0.5).build();
+        net.prune(&Sparsity::PerLayer(vec![0.5]));
+        net.widen(1, 6);
+
+        let sparsity = net.layer_sparsity();
+        net.train(&inputs, &targets, 3);
+
+        assert!(sparsity.iter().all(|s| *s > 0.));
+        assert_eq!(net.layer_sparsity(), sparsity);
+    }
+}
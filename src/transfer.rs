@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use serde_derive::Deserialize;
+
+use crate::array::Array;
+use crate::error::{NetError, Result};
+use crate::format;
+use crate::linalg::*;
+use crate::net::Net;
+
+/// Layer parameters of any saved net or model file
+#[derive(Deserialize)]
+struct Layers {
+    weights: Array<Matrix>,
+    biases: Array<Vector>
+}
+
+impl<const L: usize> Net<L> {
+    /// Excludes weight layer l, indexed from the input, from training
+    pub fn freeze(&mut self, layer: usize) -> &mut Self {
+        if layer >= L-1 {
+            panic!("{}", NetError::LayerCount { expected: L-1, found: layer+1 })
+        }
+
+        if !self.data.frozen.contains(&layer) {
+            self.data.frozen.push(layer);
+        }
+
+        self
+    }
+
+    pub fn unfreeze(&mut self, layer: usize) -> &mut Self {
+        self.data.frozen.retain(|l| *l != layer);
+        self
+    }
+
+    /// Freezes every layer but the output layer
+    pub fn freeze_hidden(&mut self) -> &mut Self {
+        self.data.frozen = (0..L-2).collect();
+        self
+    }
+
+    pub fn unfreeze_all(&mut self) -> &mut Self {
+        self.data.frozen.clear();
+        self
+    }
+
+    pub fn is_frozen(&self, layer: usize) -> bool {
+        self.data.frozen.contains(&layer)
+    }
+
+    /// Replaces the output layer with a freshly initialized one of size,
+    /// keeping the hidden layers and resetting any training progress
+    pub fn replace_head(&mut self, size: usize) {
+        let mut data = self.data.clone();
+        data.form[L-1] = size;
+
+        let mut net = Self::from_parts(data);
+
+        for l in 0..L-2 {
+            net.weights[l] = self.weights[l].clone();
+            net.biases[l] = self.biases[l].clone();
+        }
+
+        net.masks = self.masks.take().map(|mut masks| {
+            masks[L-2] = Matrix::from_fill(net.weights[L-2].shape(), 1.);
+            masks
+        });
+
+        *self = net;
+    }
+
+    /// Copies the parameters of every layer matching this net's layer
+    /// of the same index in shape, returning the copied layer indices
+    pub fn load_layers_from<P: AsRef<Path>>(&mut self, path: P) -> Result<Vec<usize>> {
+        let bytes = std::fs::read(path)?;
+
+        let layers = if format::is_binary(&bytes) {
+            let binary = format::from_binary(&bytes)?;
+            Layers { weights: Array::from_buf(binary.weights), biases: Array::from_buf(binary.biases) }
+        }
+        else {
+            format::from_versioned(&bytes)?
+        };
+
+        Ok(self.copy_matching(&layers.weights.buf, &layers.biases.buf))
+    }
+
+    /// Copies the parameters of every layer of other matching this
+    /// net's layer of the same index in shape
+    pub fn copy_layers_from<const M: usize>(&mut self, other: &Net<M>) -> Vec<usize> {
+        self.copy_matching(&other.weights.buf, &other.biases.buf)
+    }
+
+    fn copy_matching(&mut self, weights: &[Matrix], biases: &[Vector]) -> Vec<usize> {
+        let mut copied = Vec::new();
+
+        for (l, (w, b)) in weights.iter().zip(biases).enumerate().take(L-1) {
+            if w.shape() == self.weights[l].shape() && b.shape() == self.biases[l].shape() {
+                self.weights[l] = w.clone();
+                self.biases[l] = b.clone();
+
+                if let Some(masks) = self.masks.as_mut() {
+                    masks[l] = Matrix::from_fill(w.shape(), 1.);
+                }
+
+                copied.push(l);
+            }
+        }
+
+        copied
+    }
+}
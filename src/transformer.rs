@@ -0,0 +1,928 @@
+use std::io::{Read, Write};
+use std::path::Path;
+
+use serde_derive::{Serialize, Deserialize};
+
+use crate::array::Array;
+use crate::cost::Cost;
+use crate::error::{NetError, Result};
+use crate::format;
+use crate::step::Activation;
+use crate::linalg::*;
+
+/// Default layer normalization epsilon
+const NORM_EPS: f32 = 1e-5;
+
+// Sequences are stored as matrices with one token per row,
+// errors follow the `Net` convention of holding the negative
+// cost gradient, so gradients are applied by addition
+
+/// Layer normalization over the features of each token
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LayerNorm {
+    gain: Vector,
+
+    shift: Vector,
+
+    eps: f32,
+
+    /// Training Data ///
+
+    // normalized input buffer
+    norm: Matrix,
+
+    // inverse standard deviation buffer
+    inv_std: Vector,
+
+    // gain error accumulator
+    acc_gain_err: Vector,
+
+    // shift error accumulator
+    acc_shift_err: Vector,
+}
+
+impl LayerNorm {
+    pub fn new(dim: usize) -> Self {
+        Self {
+            gain: Vector::from_fill(dim, 1.),
+            shift: Vector::from_zeros(dim),
+            eps: NORM_EPS,
+            norm: Matrix::from_zeros((0, dim)),
+            inv_std: Vector::from_zeros(0),
+            acc_gain_err: Vector::from_zeros(dim),
+            acc_shift_err: Vector::from_zeros(dim),
+        }
+    }
+
+    pub fn forward_prop(&mut self, input: &Matrix) -> Matrix {
+        if input.col() != self.gain.row() {
+            panic!("expected tokens with {} features, found shape {:?}!", self.gain.row(), input.shape())
+        }
+
+        let dim = input.col() as f32;
+
+        self.norm = Matrix::from_zeros(input.shape());
+        self.inv_std = Vector::from_zeros(input.row());
+
+        for r in 0..input.row() {
+            let mean = (0..input.col()).map(|c| input[(r, c)]).sum::<f32>() / dim;
+            let var = (0..input.col()).map(|c| (input[(r, c)] - mean).powi(2)).sum::<f32>() / dim;
+
+            self.inv_std[r] = 1. / (var + self.eps).sqrt();
+
+            for c in 0..input.col() {
+                self.norm[(r, c)] = (input[(r, c)] - mean) * self.inv_std[r];
+            }
+        }
+
+        Matrix::from_map(input.shape(), |(r, c)| self.gain[c] * self.norm[(r, c)] + self.shift[c])
+    }
+
+    pub fn back_prop(&mut self, err: &Matrix) -> Matrix {
+        let dim = err.col() as f32;
+        let mut in_err = Matrix::from_zeros(err.shape());
+
+        for r in 0..err.row() {
+            let mut sum = 0.;
+            let mut sum_norm = 0.;
+
+            for c in 0..err.col() {
+                let norm_err = err[(r, c)] * self.gain[c];
+
+                sum += norm_err;
+                sum_norm += norm_err * self.norm[(r, c)];
+
+                self.acc_gain_err[c] += err[(r, c)] * self.norm[(r, c)];
+                self.acc_shift_err[c] += err[(r, c)];
+            }
+
+            // error_x = inv_std / n * ( n * error_norm - sum(error_norm) - norm * sum(error_norm . norm) )
+            for c in 0..err.col() {
+                let norm_err = err[(r, c)] * self.gain[c];
+                in_err[(r, c)] = self.inv_std[r] / dim * (dim * norm_err - sum - self.norm[(r, c)] * sum_norm);
+            }
+        }
+
+        in_err
+    }
+
+    pub fn apply_gradient(&mut self, learn_rate: f32) {
+        self.gain.add_eq(&self.acc_gain_err.scale(learn_rate));
+        self.shift.add_eq(&self.acc_shift_err.scale(learn_rate));
+    }
+
+    pub fn clear_accumulation_data(&mut self) {
+        self.acc_gain_err.fill_zero();
+        self.acc_shift_err.fill_zero();
+    }
+
+    /// Fails if the parameters don't normalize dim features
+    fn check_form(&self, dim: usize) -> Result<()> {
+        for vec in [&self.gain, &self.shift, &self.acc_gain_err, &self.acc_shift_err] {
+            check_shape((dim, 1), vec.shape())?;
+        }
+
+        check_shape((self.norm.row(), dim), self.norm.shape())
+    }
+}
+
+/// Position-wise two layer feed-forward block
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FeedForward {
+    w_in: Matrix,
+
+    b_in: Vector,
+
+    w_out: Matrix,
+
+    b_out: Vector,
+
+    act: Activation,
+
+    /// Training Data ///
+
+    // input buffer
+    input: Matrix,
+
+    // hidden summations buffer
+    sums: Matrix,
+
+    // hidden activations buffer
+    acts: Matrix,
+
+    // input weight error accumulator
+    acc_w_in_err: Matrix,
+
+    // input bias error accumulator
+    acc_b_in_err: Vector,
+
+    // output weight error accumulator
+    acc_w_out_err: Matrix,
+
+    // output bias error accumulator
+    acc_b_out_err: Vector,
+}
+
+impl FeedForward {
+    pub fn new(dim: usize, hidden: usize, act: Activation) -> Self {
+        Self {
+            w_in: rand_scaled((dim, hidden)),
+            b_in: Vector::from_zeros(hidden),
+            w_out: rand_scaled((hidden, dim)),
+            b_out: Vector::from_zeros(dim),
+            act,
+            input: Matrix::from_zeros((0, dim)),
+            sums: Matrix::from_zeros((0, hidden)),
+            acts: Matrix::from_zeros((0, hidden)),
+            acc_w_in_err: Matrix::from_zeros((dim, hidden)),
+            acc_b_in_err: Vector::from_zeros(hidden),
+            acc_w_out_err: Matrix::from_zeros((hidden, dim)),
+            acc_b_out_err: Vector::from_zeros(dim),
+        }
+    }
+
+    pub fn forward_prop(&mut self, input: &Matrix) -> Matrix {
+        self.input = input.clone();
+        self.sums = Matrix::from_zeros((input.row(), self.w_in.col()));
+
+        // sums = input x w_in + b_in
+        input.mul_to(&self.w_in, &mut self.sums);
+        add_rows_eq(&mut self.sums, &self.b_in);
+
+        self.acts = self.sums.map(|n| self.act.value(n));
+
+        // out = step ( sums ) x w_out + b_out
+        let mut out = Matrix::from_zeros((input.row(), self.w_out.col()));
+        self.acts.mul_to(&self.w_out, &mut out);
+        add_rows_eq(&mut out, &self.b_out);
+
+        out
+    }
+
+    pub fn back_prop(&mut self, err: &Matrix) -> Matrix {
+        // w_out_err = acts ^ T x err
+        let mut w_out_err = Matrix::from_zeros(self.w_out.shape());
+        self.acts.mul_t1_to(err, &mut w_out_err);
+        self.acc_w_out_err.add_eq(&w_out_err);
+        self.acc_b_out_err.add_eq(&sum_rows(err));
+
+        // hidden_err = err x w_out ^ T . step' ( sums )
+        let mut hidden_err = Matrix::from_zeros(self.sums.shape());
+        err.mul_t2_to(&self.w_out, &mut hidden_err);
+        hidden_err.dot_eq(&self.sums.map(|n| self.act.deriv(n)));
+
+        // w_in_err = input ^ T x hidden_err
+        let mut w_in_err = Matrix::from_zeros(self.w_in.shape());
+        self.input.mul_t1_to(&hidden_err, &mut w_in_err);
+        self.acc_w_in_err.add_eq(&w_in_err);
+        self.acc_b_in_err.add_eq(&sum_rows(&hidden_err));
+
+        // in_err = hidden_err x w_in ^ T
+        let mut in_err = Matrix::from_zeros(self.input.shape());
+        hidden_err.mul_t2_to(&self.w_in, &mut in_err);
+
+        in_err
+    }
+
+    pub fn apply_gradient(&mut self, learn_rate: f32) {
+        self.w_in.add_eq(&self.acc_w_in_err.scale(learn_rate));
+        self.b_in.add_eq(&self.acc_b_in_err.scale(learn_rate));
+        self.w_out.add_eq(&self.acc_w_out_err.scale(learn_rate));
+        self.b_out.add_eq(&self.acc_b_out_err.scale(learn_rate));
+    }
+
+    pub fn clear_accumulation_data(&mut self) {
+        self.acc_w_in_err.fill_zero();
+        self.acc_b_in_err.fill_zero();
+        self.acc_w_out_err.fill_zero();
+        self.acc_b_out_err.fill_zero();
+    }
+
+    /// Fails if the parameters don't map dim features through a hidden layer
+    fn check_form(&self, dim: usize) -> Result<()> {
+        let hidden = self.w_in.col();
+
+        check_shape((dim, hidden), self.w_in.shape())?;
+        check_shape((dim, hidden), self.acc_w_in_err.shape())?;
+        check_shape((hidden, dim), self.w_out.shape())?;
+        check_shape((hidden, dim), self.acc_w_out_err.shape())?;
+        check_shape((hidden, 1), self.b_in.shape())?;
+        check_shape((hidden, 1), self.acc_b_in_err.shape())?;
+        check_shape((dim, 1), self.b_out.shape())?;
+        check_shape((dim, 1), self.acc_b_out_err.shape())
+    }
+}
+
+/// Multi-head scaled dot-product self-attention
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SelfAttention {
+    // per head query projections
+    w_query: Array<Matrix>,
+
+    // per head key projections
+    w_key: Array<Matrix>,
+
+    // per head value projections
+    w_value: Array<Matrix>,
+
+    // per head output projections
+    w_out: Array<Matrix>,
+
+    b_out: Vector,
+
+    /// Training Data ///
+
+    // input buffer
+    input: Matrix,
+
+    // per head query buffer
+    query: Array<Matrix>,
+
+    // per head key buffer
+    key: Array<Matrix>,
+
+    // per head value buffer
+    value: Array<Matrix>,
+
+    // per head attention weights buffer
+    attn: Array<Matrix>,
+
+    // per head attended values buffer
+    heads: Array<Matrix>,
+
+    // query weight error accumulator
+    acc_w_query_err: Array<Matrix>,
+
+    // key weight error accumulator
+    acc_w_key_err: Array<Matrix>,
+
+    // value weight error accumulator
+    acc_w_value_err: Array<Matrix>,
+
+    // output weight error accumulator
+    acc_w_out_err: Array<Matrix>,
+
+    // output bias error accumulator
+    acc_b_out_err: Vector,
+}
+
+impl SelfAttention {
+    pub fn new(dim: usize, heads: usize) -> Self {
+        if heads == 0 || !dim.is_multiple_of(heads) {
+            panic!("cannot split {} features across {} heads!", dim, heads)
+        }
+
+        let head_dim = dim / heads;
+
+        let rand = |shape| Array::from_buf((0..heads).map(|_| rand_scaled(shape)).collect());
+        let zeros = |shape| Array::from_buf((0..heads).map(|_| Matrix::from_zeros(shape)).collect());
+
+        Self {
+            w_query: rand((dim, head_dim)),
+            w_key: rand((dim, head_dim)),
+            w_value: rand((dim, head_dim)),
+            w_out: rand((head_dim, dim)),
+            b_out: Vector::from_zeros(dim),
+            input: Matrix::from_zeros((0, dim)),
+            query: zeros((0, head_dim)),
+            key: zeros((0, head_dim)),
+            value: zeros((0, head_dim)),
+            attn: zeros((0, 0)),
+            heads: zeros((0, head_dim)),
+            acc_w_query_err: zeros((dim, head_dim)),
+            acc_w_key_err: zeros((dim, head_dim)),
+            acc_w_value_err: zeros((dim, head_dim)),
+            acc_w_out_err: zeros((head_dim, dim)),
+            acc_b_out_err: Vector::from_zeros(dim),
+        }
+    }
+
+    pub fn head_count(&self) -> usize {
+        self.w_query.len()
+    }
+
+    fn head_dim(&self) -> usize {
+        self.w_query[0].col()
+    }
+
+    pub fn forward_prop(&mut self, input: &Matrix) -> Matrix {
+        if input.col() != self.b_out.row() {
+            panic!("expected tokens with {} features, found shape {:?}!", self.b_out.row(), input.shape())
+        }
+
+        let seq = input.row();
+        let head_dim = self.head_dim();
+        let scale = 1. / (head_dim as f32).sqrt();
+
+        self.input = input.clone();
+
+        let mut out = Matrix::from_zeros(input.shape());
+        let mut head_out = Matrix::from_zeros(input.shape());
+
+        for h in 0..self.head_count() {
+            self.query[h] = Matrix::from_zeros((seq, head_dim));
+            self.key[h] = Matrix::from_zeros((seq, head_dim));
+            self.value[h] = Matrix::from_zeros((seq, head_dim));
+            self.attn[h] = Matrix::from_zeros((seq, seq));
+            self.heads[h] = Matrix::from_zeros((seq, head_dim));
+
+            input.mul_to(&self.w_query[h], &mut self.query[h]);
+            input.mul_to(&self.w_key[h], &mut self.key[h]);
+            input.mul_to(&self.w_value[h], &mut self.value[h]);
+
+            // attn = softmax ( query x key ^ T / sqrt(d) )
+            self.query[h].mul_t2_to(&self.key[h], &mut self.attn[h]);
+            self.attn[h].scale_eq(scale);
+            softmax_rows_eq(&mut self.attn[h]);
+
+            // head = attn x value
+            self.attn[h].mul_to(&self.value[h], &mut self.heads[h]);
+
+            // out = sum ( head x w_out ) + b_out
+            self.heads[h].mul_to(&self.w_out[h], &mut head_out);
+            out.add_eq(&head_out);
+        }
+
+        add_rows_eq(&mut out, &self.b_out);
+
+        out
+    }
+
+    pub fn back_prop(&mut self, err: &Matrix) -> Matrix {
+        let seq = err.row();
+        let head_dim = self.head_dim();
+        let scale = 1. / (head_dim as f32).sqrt();
+
+        self.acc_b_out_err.add_eq(&sum_rows(err));
+
+        let mut in_err = Matrix::from_zeros(self.input.shape());
+        let mut proj_err = Matrix::from_zeros(self.input.shape());
+        let mut w_err = Matrix::from_zeros(self.w_query[0].shape());
+        let mut w_out_err = Matrix::from_zeros(self.w_out[0].shape());
+
+        for h in 0..self.head_count() {
+            // w_out_err = head ^ T x err
+            self.heads[h].mul_t1_to(err, &mut w_out_err);
+            self.acc_w_out_err[h].add_eq(&w_out_err);
+
+            // head_err = err x w_out ^ T
+            let mut head_err = Matrix::from_zeros((seq, head_dim));
+            err.mul_t2_to(&self.w_out[h], &mut head_err);
+
+            // attn_err = head_err x value ^ T
+            let mut attn_err = Matrix::from_zeros((seq, seq));
+            head_err.mul_t2_to(&self.value[h], &mut attn_err);
+
+            // value_err = attn ^ T x head_err
+            let mut value_err = Matrix::from_zeros((seq, head_dim));
+            self.attn[h].mul_t1_to(&head_err, &mut value_err);
+
+            // score_err = softmax' ( attn_err ) / sqrt(d)
+            let mut score_err = softmax_rows_deriv(&self.attn[h], &attn_err);
+            score_err.scale_eq(scale);
+
+            // query_err = score_err x key, key_err = score_err ^ T x query
+            let mut query_err = Matrix::from_zeros((seq, head_dim));
+            let mut key_err = Matrix::from_zeros((seq, head_dim));
+            score_err.mul_to(&self.key[h], &mut query_err);
+            score_err.mul_t1_to(&self.query[h], &mut key_err);
+
+            // w_err = input ^ T x proj_err, in_err += proj_err x w ^ T
+            let projections = [
+                (&query_err, &self.w_query[h], &mut self.acc_w_query_err[h]),
+                (&key_err, &self.w_key[h], &mut self.acc_w_key_err[h]),
+                (&value_err, &self.w_value[h], &mut self.acc_w_value_err[h]),
+            ];
+
+            for (err, w, acc_w_err) in projections {
+                self.input.mul_t1_to(err, &mut w_err);
+                acc_w_err.add_eq(&w_err);
+
+                err.mul_t2_to(w, &mut proj_err);
+                in_err.add_eq(&proj_err);
+            }
+        }
+
+        in_err
+    }
+
+    pub fn apply_gradient(&mut self, learn_rate: f32) {
+        for h in 0..self.head_count() {
+            self.w_query[h].add_eq(&self.acc_w_query_err[h].scale(learn_rate));
+            self.w_key[h].add_eq(&self.acc_w_key_err[h].scale(learn_rate));
+            self.w_value[h].add_eq(&self.acc_w_value_err[h].scale(learn_rate));
+            self.w_out[h].add_eq(&self.acc_w_out_err[h].scale(learn_rate));
+        }
+
+        self.b_out.add_eq(&self.acc_b_out_err.scale(learn_rate));
+    }
+
+    pub fn clear_accumulation_data(&mut self) {
+        self.acc_w_query_err.zero();
+        self.acc_w_key_err.zero();
+        self.acc_w_value_err.zero();
+        self.acc_w_out_err.zero();
+        self.acc_b_out_err.fill_zero();
+    }
+
+    /// Fails if the heads don't split dim features evenly
+    fn check_form(&self, dim: usize) -> Result<()> {
+        let heads = self.head_count();
+
+        if heads == 0 || !dim.is_multiple_of(heads) {
+            return Err(NetError::Heads { dim, heads })
+        }
+
+        let head_dim = dim / heads;
+
+        let per_head = [
+            (&self.w_query, (dim, head_dim)),
+            (&self.w_key, (dim, head_dim)),
+            (&self.w_value, (dim, head_dim)),
+            (&self.w_out, (head_dim, dim)),
+            (&self.acc_w_query_err, (dim, head_dim)),
+            (&self.acc_w_key_err, (dim, head_dim)),
+            (&self.acc_w_value_err, (dim, head_dim)),
+            (&self.acc_w_out_err, (head_dim, dim)),
+        ];
+
+        for (mats, shape) in per_head {
+            if mats.len() != heads {
+                return Err(NetError::LayerCount { expected: heads, found: mats.len() })
+            }
+
+            for mat in mats.buf.iter() {
+                check_shape(shape, mat.shape())?;
+            }
+        }
+
+        for buf in [&self.query, &self.key, &self.value, &self.attn, &self.heads] {
+            if buf.len() != heads {
+                return Err(NetError::LayerCount { expected: heads, found: buf.len() })
+            }
+        }
+
+        check_shape((dim, 1), self.b_out.shape())?;
+        check_shape((dim, 1), self.acc_b_out_err.shape())
+    }
+}
+
+/// Post-norm transformer encoder layer:
+/// norm ( x + attn(x) ) followed by norm ( y + ff(y) )
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EncoderLayer {
+    attn: SelfAttention,
+
+    attn_norm: LayerNorm,
+
+    ff: FeedForward,
+
+    ff_norm: LayerNorm,
+}
+
+impl EncoderLayer {
+    pub fn new(dim: usize, heads: usize, hidden: usize, act: Activation) -> Self {
+        Self {
+            attn: SelfAttention::new(dim, heads),
+            attn_norm: LayerNorm::new(dim),
+            ff: FeedForward::new(dim, hidden, act),
+            ff_norm: LayerNorm::new(dim),
+        }
+    }
+
+    pub fn forward_prop(&mut self, input: &Matrix) -> Matrix {
+        let attended = self.attn.forward_prop(input).add(input);
+        let attended = self.attn_norm.forward_prop(&attended);
+
+        let fed = self.ff.forward_prop(&attended).add(&attended);
+        self.ff_norm.forward_prop(&fed)
+    }
+
+    pub fn back_prop(&mut self, err: &Matrix) -> Matrix {
+        let fed_err = self.ff_norm.back_prop(err);
+        let attended_err = self.ff.back_prop(&fed_err).add(&fed_err);
+
+        let attended_err = self.attn_norm.back_prop(&attended_err);
+        self.attn.back_prop(&attended_err).add(&attended_err)
+    }
+
+    pub fn apply_gradient(&mut self, learn_rate: f32) {
+        self.attn.apply_gradient(learn_rate);
+        self.attn_norm.apply_gradient(learn_rate);
+        self.ff.apply_gradient(learn_rate);
+        self.ff_norm.apply_gradient(learn_rate);
+    }
+
+    pub fn clear_accumulation_data(&mut self) {
+        self.attn.clear_accumulation_data();
+        self.attn_norm.clear_accumulation_data();
+        self.ff.clear_accumulation_data();
+        self.ff_norm.clear_accumulation_data();
+    }
+
+    /// Returns the number of features of every token
+    pub fn dim(&self) -> usize {
+        self.attn.b_out.row()
+    }
+
+    /// Returns the summed cost of the encoded input against target
+    pub fn loss(&mut self, input: &Matrix, target: &Matrix, cost: Cost) -> f32 {
+        let out = self.forward_prop(input);
+
+        out.buf()
+            .iter()
+            .zip(target.buf())
+            .map(|(a, y)| cost.value(*a, *y))
+            .sum()
+    }
+
+    /// Trains on sequences and their target encodings, applying the averaged
+    /// gradient every batch and returning the mean loss of every epoch
+    pub fn train(&mut self, inputs: &[Matrix], targets: &[Matrix], cost: Cost, learn_rate: f32, batch_size: usize, epochs: usize) -> Vec<f32> {
+        self.try_train(inputs, targets, cost, learn_rate, batch_size, epochs)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub fn try_train(&mut self, inputs: &[Matrix], targets: &[Matrix], cost: Cost, learn_rate: f32, batch_size: usize, epochs: usize) -> Result<Vec<f32>> {
+        if inputs.len() != targets.len() {
+            return Err(NetError::Length { inputs: inputs.len(), targets: targets.len() })
+        }
+
+        for (input, target) in inputs.iter().zip(targets) {
+            check_shape((input.row(), self.dim()), input.shape())?;
+            check_shape(input.shape(), target.shape())?;
+        }
+
+        let batch_size = batch_size.max(1);
+        let mut losses = Vec::with_capacity(epochs);
+
+        for _ in 0..epochs {
+            let mut total = 0.;
+            let mut samples = 0;
+
+            self.clear_accumulation_data();
+
+            for (n, (input, target)) in inputs.iter().zip(targets).enumerate() {
+                let out = self.forward_prop(input);
+                let err = Matrix::from_map(out.shape(), |rc| cost.deriv(out[rc], target[rc]));
+
+                total += out.buf().iter().zip(target.buf()).map(|(a, y)| cost.value(*a, *y)).sum::<f32>();
+
+                self.back_prop(&err);
+                samples += 1;
+
+                if samples == batch_size || n == inputs.len()-1 {
+                    self.apply_gradient(learn_rate / samples as f32);
+                    self.clear_accumulation_data();
+                    samples = 0;
+                }
+            }
+
+            losses.push(total / inputs.len().max(1) as f32);
+        }
+
+        Ok(losses)
+    }
+
+    /// Atomically writes the versioned layer to path
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        format::save_versioned(self, path)
+    }
+
+    /// Writes the versioned layer to writer
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<()> {
+        format::write_versioned(self, writer)
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Self {
+        Self::try_from_file(path)
+            .unwrap_or_else(|err| panic!("couldn't load encoder layer: {}", err))
+    }
+
+    pub fn try_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::read_from(format::open(path)?)
+    }
+
+    pub fn read_from<R: Read>(reader: R) -> Result<Self> {
+        let bytes = format::read_all(reader)?;
+
+        let layer: Self = format::from_versioned(&bytes)?;
+        layer.check_form()?;
+
+        Ok(layer)
+    }
+
+    /// Fails if the sublayers don't share their number of features
+    fn check_form(&self) -> Result<()> {
+        let dim = self.dim();
+
+        self.attn.check_form(dim)?;
+        self.attn_norm.check_form(dim)?;
+        self.ff.check_form(dim)?;
+        self.ff_norm.check_form(dim)
+    }
+}
+
+fn check_shape(expected: (usize, usize), found: (usize, usize)) -> Result<()> {
+    if expected != found {
+        return Err(NetError::Shape { expected, found })
+    }
+
+    Ok(())
+}
+
+/// Returns matrix with random values scaled by fan-in
+fn rand_scaled(shape: (usize, usize)) -> Matrix {
+    Matrix::rand(shape).scale(1. / (shape.0 as f32).sqrt())
+}
+
+/// Adds vector to every row of matrix
+fn add_rows_eq(mat: &mut Matrix, vec: &Vector) {
+    for r in 0..mat.row() {
+        for c in 0..mat.col() {
+            mat[(r, c)] += vec[c];
+        }
+    }
+}
+
+/// Returns the column-wise sum of all rows
+fn sum_rows(mat: &Matrix) -> Vector {
+    Vector::from_map(mat.col(), |c| (0..mat.row()).map(|r| mat[(r, c)]).sum())
+}
+
+/// Applies softmax to every row of matrix
+fn softmax_rows_eq(mat: &mut Matrix) {
+    for r in 0..mat.row() {
+        let max = (0..mat.col()).fold(f32::MIN, |max, c| max.max(mat[(r, c)]));
+
+        for c in 0..mat.col() {
+            mat[(r, c)] = (mat[(r, c)] - max).exp();
+        }
+
+        let sum: f32 = (0..mat.col()).map(|c| mat[(r, c)]).sum();
+
+        for c in 0..mat.col() {
+            mat[(r, c)] /= sum;
+        }
+    }
+}
+
+/// Propagates error through row-wise softmax:
+/// error_ij = soft_ij * ( err_ij - sum_k ( err_ik * soft_ik ) )
+fn softmax_rows_deriv(soft: &Matrix, err: &Matrix) -> Matrix {
+    let mut out = Matrix::from_zeros(soft.shape());
+
+    for r in 0..soft.row() {
+        let dot: f32 = (0..soft.col()).map(|c| err[(r, c)] * soft[(r, c)]).sum();
+
+        for c in 0..soft.col() {
+            out[(r, c)] = soft[(r, c)] * (err[(r, c)] - dot);
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gradient::CHECK_EPSILON;
+
+    const TOLERANCE: f32 = 5e-2;
+
+    /// Lower bound of the relative error denominator, above the rounding
+    /// of central differences of the summed f32 cost of a whole sequence
+    const CHECK_FLOOR: f32 = 1e-2;
+
+    fn relative_error(numeric: f32, analytic: f32) -> f32 {
+        (numeric - analytic).abs() / (numeric.abs() + analytic.abs()).max(CHECK_FLOOR)
+    }
+
+    /// Layer under a gradient check, exposing its parameters
+    /// together with their accumulated errors
+    trait Checked {
+        fn forward(&mut self, input: &Matrix) -> Matrix;
+
+        fn backward(&mut self, err: &Matrix) -> Matrix;
+
+        fn params(&mut self) -> Vec<(&mut [f32], &[f32])>;
+    }
+
+    impl Checked for LayerNorm {
+        fn forward(&mut self, input: &Matrix) -> Matrix {
+            self.forward_prop(input)
+        }
+
+        fn backward(&mut self, err: &Matrix) -> Matrix {
+            self.back_prop(err)
+        }
+
+        fn params(&mut self) -> Vec<(&mut [f32], &[f32])> {
+            vec![
+                (self.gain.buf_mut(), self.acc_gain_err.buf()),
+                (self.shift.buf_mut(), self.acc_shift_err.buf())
+            ]
+        }
+    }
+
+    impl Checked for FeedForward {
+        fn forward(&mut self, input: &Matrix) -> Matrix {
+            self.forward_prop(input)
+        }
+
+        fn backward(&mut self, err: &Matrix) -> Matrix {
+            self.back_prop(err)
+        }
+
+        fn params(&mut self) -> Vec<(&mut [f32], &[f32])> {
+            vec![
+                (self.w_in.buf_mut(), self.acc_w_in_err.buf()),
+                (self.b_in.buf_mut(), self.acc_b_in_err.buf()),
+                (self.w_out.buf_mut(), self.acc_w_out_err.buf()),
+                (self.b_out.buf_mut(), self.acc_b_out_err.buf())
+            ]
+        }
+    }
+
+    impl Checked for SelfAttention {
+        fn forward(&mut self, input: &Matrix) -> Matrix {
+            self.forward_prop(input)
+        }
+
+        fn backward(&mut self, err: &Matrix) -> Matrix {
+            self.back_prop(err)
+        }
+
+        fn params(&mut self) -> Vec<(&mut [f32], &[f32])> {
+            let projections = [
+                (&mut self.w_query, &self.acc_w_query_err),
+                (&mut self.w_key, &self.acc_w_key_err),
+                (&mut self.w_value, &self.acc_w_value_err),
+                (&mut self.w_out, &self.acc_w_out_err)
+            ];
+
+            let mut params: Vec<(&mut [f32], &[f32])> = projections
+                .into_iter()
+                .flat_map(|(w, acc)| w.buf.iter_mut().zip(acc.buf.iter()))
+                .map(|(w, acc)| (w.buf_mut().as_mut_slice(), acc.buf().as_slice()))
+                .collect();
+
+            params.push((self.b_out.buf_mut(), self.acc_b_out_err.buf()));
+            params
+        }
+    }
+
+    impl Checked for EncoderLayer {
+        fn forward(&mut self, input: &Matrix) -> Matrix {
+            self.forward_prop(input)
+        }
+
+        fn backward(&mut self, err: &Matrix) -> Matrix {
+            self.back_prop(err)
+        }
+
+        fn params(&mut self) -> Vec<(&mut [f32], &[f32])> {
+            let mut params = self.attn.params();
+            params.extend(self.attn_norm.params());
+            params.extend(self.ff.params());
+            params.extend(self.ff_norm.params());
+            params
+        }
+    }
+
+    fn loss<T: Checked>(layer: &mut T, input: &Matrix, target: &Matrix) -> f32 {
+        let out = layer.forward(input);
+
+        out.buf()
+            .iter()
+            .zip(target.buf())
+            .map(|(a, y)| Cost::Quad.value(*a, *y))
+            .sum()
+    }
+
+    /// Returns the largest relative error between the back-propagated
+    /// and the central difference gradients of the inputs and parameters
+    fn gradient_check<T: Checked>(layer: &mut T, input: &Matrix, target: &Matrix) -> f32 {
+        let out = layer.forward(input);
+        let err = Matrix::from_map(out.shape(), |rc| Cost::Quad.deriv(out[rc], target[rc]));
+        let in_err = layer.backward(&err);
+
+        let mut worst: f32 = 0.;
+
+        for i in 0..input.buf().len() {
+            let mut plus = input.clone();
+            let mut minus = input.clone();
+            plus.buf_mut()[i] += CHECK_EPSILON;
+            minus.buf_mut()[i] -= CHECK_EPSILON;
+
+            let numeric = (loss(layer, &plus, target) - loss(layer, &minus, target)) / (2. * CHECK_EPSILON);
+            worst = worst.max(relative_error(numeric, -in_err.buf()[i]));
+        }
+
+        let errors: Vec<Vec<f32>> = layer.params().into_iter().map(|(_, err)| err.to_vec()).collect();
+
+        for (k, err) in errors.iter().enumerate() {
+            for (i, analytic) in err.iter().enumerate() {
+                let value = layer.params()[k].0[i];
+
+                layer.params()[k].0[i] = value + CHECK_EPSILON;
+                let plus = loss(layer, input, target);
+
+                layer.params()[k].0[i] = value - CHECK_EPSILON;
+                let minus = loss(layer, input, target);
+
+                layer.params()[k].0[i] = value;
+
+                let numeric = (plus - minus) / (2. * CHECK_EPSILON);
+                worst = worst.max(relative_error(numeric, -analytic));
+            }
+        }
+
+        worst
+    }
+
+    /// Returns a fixed sequence and target whose tokens have well spread
+    /// features, keeping the layer norms away from near zero variances
+    fn sequence() -> (Matrix, Matrix) {
+        let input = Matrix::from_map((3, 4), |(r, c)| ((4 * r + c) as f32 * 1.3).sin());
+        let target = Matrix::from_map((3, 4), |(r, c)| ((4 * r + c) as f32 * 0.7).cos());
+
+        (input, target)
+    }
+
+    #[test]
+    fn layer_norm_gradient() {
+        let (input, target) = sequence();
+        let mut norm = LayerNorm::new(4);
+        norm.gain = Vector::from_buf(4, vec![0.5, 1.5, -1., 2.]);
+
+        let error = gradient_check(&mut norm, &input, &target);
+        assert!(error < TOLERANCE, "layer norm: {}", error);
+    }
+
+    #[test]
+    fn feed_forward_gradient() {
+        let (input, target) = sequence();
+
+        for act in [Activation::Sig, Activation::Tanh, Activation::Lin] {
+            let error = gradient_check(&mut FeedForward::new(4, 6, act), &input, &target);
+            assert!(error < TOLERANCE, "feed forward {:?}: {}", act, error);
+        }
+    }
+
+    #[test]
+    fn self_attention_gradient() {
+        let (input, target) = sequence();
+
+        let error = gradient_check(&mut SelfAttention::new(4, 2), &input, &target);
+        assert!(error < TOLERANCE, "self attention: {}", error);
+    }
+
+    #[test]
+    fn encoder_layer_gradient() {
+        let (input, target) = sequence();
+
+        let error = gradient_check(&mut EncoderLayer::new(4, 2, 6, Activation::Tanh), &input, &target);
+        assert!(error < TOLERANCE, "encoder layer: {}", error);
+    }
+}